@@ -0,0 +1,788 @@
+use std::{fmt, fs, io};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sevenz_rust::{Password, SevenZReader};
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+use crate::progress::{Phase, Progress};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    SevenZip,
+}
+
+// Caps that keep a broken or hostile archive from filling the disk
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExtractionLimits {
+    pub max_total_size: u64, // Bytes written across every entry
+    pub max_entries: usize,
+    pub max_ratio: u64, // Extracted bytes per byte of archive
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        ExtractionLimits {
+            max_total_size: 8 * 1024 * 1024 * 1024,
+            max_entries: 100_000,
+            max_ratio: 100,
+        }
+    }
+}
+
+// Tiny archives compress unusually well, so the ratio only counts past this much output
+const RATIO_GRACE_BYTES: u64 = 1024 * 1024;
+// xz doesn't say how big its contents are without walking its index; releases usually shrink about this much
+const XZ_SPACE_RATIO: u64 = 4;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    UnknownFormat(String),
+    Io(io::Error),
+    Corrupt(String),
+    UnsafeLink(String), // A link pointing outside the app directory, or an entry written through one
+    UnsafePath(String), // An absolute path or one climbing out with ..
+    TooManyEntries(usize), // The limit that was hit
+    TooLarge(u64),
+    SuspiciousRatio(u64),
+    InsufficientSpace { needed: u64, available: u64 },
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::UnknownFormat(file) => write!(f, "{} is not a supported archive", file),
+            ArchiveError::Io(error) => write!(f, "Failed to extract: {}", error),
+            ArchiveError::Corrupt(error) => write!(f, "The archive is damaged: {}", error),
+            ArchiveError::UnsafeLink(entry) => write!(f, "The archive links outside the app folder at {}", entry),
+            ArchiveError::UnsafePath(entry) => write!(f, "The archive tried to write outside the app folder: {}", entry),
+            ArchiveError::TooManyEntries(limit) => write!(f, "The archive has more than {} files", limit),
+            ArchiveError::TooLarge(limit) => write!(f, "The archive unpacks to more than {}", format_size(*limit)),
+            ArchiveError::SuspiciousRatio(limit) => write!(f, "The archive expands more than {} times its size", limit),
+            ArchiveError::InsufficientSpace { needed, available } => write!(f, "Not enough disk space: {} needed, {} free", format_size(*needed), format_size(*available)),
+        }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next_unit in UNITS {
+        if size < 1024. {
+            break;
+        }
+        size /= 1024.;
+        unit = next_unit;
+    }
+    if unit == "B" {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, unit)
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        ArchiveError::Io(error)
+    }
+}
+
+// Whether a downloaded file should go through extract_archive, judging by its extension
+pub fn is_archive_name(filename: &str) -> bool {
+    format_from_extension(filename).is_some()
+}
+
+// Magic bytes win over the extension, since release names aren't always accurate
+pub fn detect_format(path: &Path) -> Option<ArchiveFormat> {
+    let mut header = [0u8; 262];
+    let read = File::open(path).and_then(|mut file| read_up_to(&mut file, &mut header)).unwrap_or(0);
+    let filename = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    format_from_magic(&header[..read]).or_else(|| format_from_extension(&filename))
+}
+
+fn format_from_magic(header: &[u8]) -> Option<ArchiveFormat> {
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        Some(ArchiveFormat::Zip)
+    } else if header.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        Some(ArchiveFormat::SevenZip)
+    } else if header.starts_with(&[0x1F, 0x8B]) {
+        // Releases only ship compressed tarballs, never a single compressed file
+        Some(ArchiveFormat::TarGz)
+    } else if header.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(ArchiveFormat::TarXz)
+    } else if header.len() >= 262 && &header[257..262] == b"ustar" {
+        Some(ArchiveFormat::Tar)
+    } else {
+        None
+    }
+}
+
+fn format_from_extension(filename: &str) -> Option<ArchiveFormat> {
+    let filename = filename.to_ascii_lowercase();
+    if filename.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if filename.ends_with(".tar.xz") || filename.ends_with(".txz") {
+        Some(ArchiveFormat::TarXz)
+    } else if filename.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else if filename.ends_with(".7z") {
+        Some(ArchiveFormat::SevenZip)
+    } else {
+        None
+    }
+}
+
+fn read_up_to(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buffer.len() {
+        match file.read(&mut buffer[total..])? {
+            0 => break,
+            read => total += read,
+        }
+    }
+    Ok(total)
+}
+
+// Unpacks into destination, reporting bytes and files through progress
+pub fn extract_archive(archive_path: &Path, destination: &Path, limits: ExtractionLimits, progress: &Progress) -> Result<(), ArchiveError> {
+    let format = detect_format(archive_path).ok_or_else(|| ArchiveError::UnknownFormat(archive_path.to_string_lossy().to_string()))?;
+    println!("Extracting {} as {:?}", archive_path.display(), format);
+    fs::create_dir_all(destination)?;
+    progress.start_phase(Phase::Extracting, 0);
+
+    let mut extraction = Extraction {
+        destination,
+        limits,
+        archive_size: fs::metadata(archive_path)?.len(),
+        written: 0,
+        entries: 0,
+        progress,
+        // Tarballs report how much of the archive was read instead
+        count_written: matches!(format, ArchiveFormat::Zip | ArchiveFormat::SevenZip),
+    };
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, &mut extraction, progress),
+        ArchiveFormat::Tar => {
+            let unpacked = extraction.archive_size;
+            extract_tar(ProgressReader::open(archive_path, progress)?, &mut extraction, unpacked)
+        }
+        ArchiveFormat::TarGz => {
+            let unpacked = gzip_unpacked_size(archive_path).max(extraction.archive_size);
+            extract_tar(GzDecoder::new(ProgressReader::open(archive_path, progress)?), &mut extraction, unpacked)
+        }
+        ArchiveFormat::TarXz => {
+            let unpacked = extraction.archive_size.saturating_mul(XZ_SPACE_RATIO);
+            extract_tar(XzDecoder::new(ProgressReader::open(archive_path, progress)?), &mut extraction, unpacked)
+        }
+        ArchiveFormat::SevenZip => extract_7z(archive_path, &mut extraction, progress),
+    }?;
+    // Decoders may stop before the padding at the end of a tarball
+    progress.set_bytes_done(progress.bytes_total());
+    Ok(())
+}
+
+// Unix file types kept in the upper bits of a mode
+const FILE_TYPE_MASK: u32 = 0o170000;
+const SYMLINK_TYPE: u32 = 0o120000;
+// 7-Zip on Unix keeps the mode in the upper half of the Windows attributes when this bit is set
+const SEVEN_ZIP_UNIX_EXTENSION: u32 = 0x8000;
+
+enum EntryKind {
+    Directory,
+    File,
+    Symlink(PathBuf), // Relative to the link
+    HardLink(PathBuf), // Relative to the archive root
+}
+
+// Running totals checked against the limits as entries are written
+struct Extraction<'a> {
+    destination: &'a Path,
+    limits: ExtractionLimits,
+    archive_size: u64,
+    written: u64,
+    entries: usize,
+    progress: &'a Progress,
+    count_written: bool, // Whether written bytes drive the progress
+}
+
+impl Extraction<'_> {
+    // Zip and 7z list their contents up front, so obvious bombs are refused before anything is written
+    fn check_declared(&self, entries: Option<usize>, size: u64) -> Result<(), ArchiveError> {
+        if entries.is_some_and(|entries| entries > self.limits.max_entries) {
+            return Err(ArchiveError::TooManyEntries(self.limits.max_entries));
+        }
+        self.check_size(size)?;
+        self.check_space(size)
+    }
+
+    fn check_space(&self, needed: u64) -> Result<(), ArchiveError> {
+        let available = fs2::available_space(self.destination)?;
+        if available < needed {
+            return Err(ArchiveError::InsufficientSpace { needed, available });
+        }
+        Ok(())
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), ArchiveError> {
+        if size > self.limits.max_total_size {
+            return Err(ArchiveError::TooLarge(self.limits.max_total_size));
+        }
+        if size > RATIO_GRACE_BYTES && size / self.archive_size.max(1) > self.limits.max_ratio {
+            return Err(ArchiveError::SuspiciousRatio(self.limits.max_ratio));
+        }
+        Ok(())
+    }
+
+    fn write_entry(&mut self, relative_path: &Path, kind: EntryKind, mode: Option<u32>, reader: &mut dyn Read) -> Result<(), ArchiveError> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(ArchiveError::TooManyEntries(self.limits.max_entries));
+        }
+        if relative_path.as_os_str().is_empty() {
+            return Ok(()); // The archive root itself, Ex: ./
+        }
+        ensure_no_link_ancestors(self.destination, relative_path)?;
+        let extracted_file_path = self.destination.join(relative_path);
+        if let Some(parent) = extracted_file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match kind {
+            EntryKind::Directory => {
+                fs::create_dir_all(&extracted_file_path)?;
+            }
+            EntryKind::File => {
+                let mut extracted_file = File::create(&extracted_file_path)?;
+                self.copy(reader, &mut extracted_file)?;
+                self.progress.file_done();
+            }
+            EntryKind::Symlink(target) => {
+                let link_parent = relative_path.parent().unwrap_or(Path::new(""));
+                if !is_normalized(&target) || resolve_inside(link_parent, &target).is_none() {
+                    return Err(ArchiveError::UnsafeLink(relative_path.display().to_string()));
+                }
+                remove_existing(&extracted_file_path)?;
+                create_symlink(&target, &extracted_file_path)?;
+                self.progress.file_done();
+                return Ok(()); // A link's own mode means nothing
+            }
+            EntryKind::HardLink(target) => {
+                let target = resolve_inside(Path::new(""), &target).ok_or_else(|| ArchiveError::UnsafeLink(relative_path.display().to_string()))?;
+                ensure_no_link_ancestors(self.destination, &target)?;
+                // fs::copy would follow it
+                if fs::symlink_metadata(self.destination.join(&target)).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                    return Err(ArchiveError::UnsafeLink(relative_path.display().to_string()));
+                }
+                remove_existing(&extracted_file_path)?;
+                let copied = fs::copy(self.destination.join(target), &extracted_file_path)?;
+                self.written += copied;
+                self.check_size(self.written)?;
+                self.progress.file_done();
+            }
+        }
+        set_mode(&extracted_file_path, mode)?;
+        Ok(())
+    }
+
+    // io::copy, but stops as soon as the output breaks a limit
+    fn copy(&mut self, reader: &mut dyn Read, file: &mut File) -> Result<(), ArchiveError> {
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.written += read as u64;
+            self.check_size(self.written)?;
+            file.write_all(&buffer[..read])?;
+            if self.count_written {
+                self.progress.set_bytes_done(self.written);
+            }
+        }
+    }
+}
+
+fn extract_zip(archive_path: &Path, extraction: &mut Extraction<'_>, progress: &Progress) -> Result<(), ArchiveError> {
+    let file = File::open(archive_path)?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| ArchiveError::Corrupt(e.to_string()))?;
+
+    let total_files = archive.len();
+    let mut declared_size: u64 = 0;
+    let mut file_count: u64 = 0; // Directories aren't counted as files
+    for i in 0..total_files {
+        let file = archive.by_index_raw(i).map_err(|e| ArchiveError::Corrupt(e.to_string()))?;
+        declared_size = declared_size.saturating_add(file.size());
+        file_count += !file.is_dir() as u64;
+    }
+    extraction.check_declared(Some(total_files), declared_size)?;
+    progress.set_bytes_total(declared_size);
+    progress.set_files(0, file_count);
+
+    for i in 0..total_files {
+        let mut file = archive.by_index(i).map_err(|e| ArchiveError::Corrupt(e.to_string()))?;
+        let relative_path = file.enclosed_name().ok_or_else(|| ArchiveError::UnsafePath(file.name().to_string()))?;
+        let kind = if file.is_dir() {
+            EntryKind::Directory
+        } else if file.is_symlink() {
+            EntryKind::Symlink(read_link_target(&mut file)?)
+        } else {
+            EntryKind::File
+        };
+        let mode = file.unix_mode();
+        extraction.write_entry(&relative_path, kind, mode, &mut file)?;
+    }
+    Ok(())
+}
+
+// Tar is a stream, so progress follows how much of the compressed file has been read
+// Only an estimate of the unpacked size is known up front; the limits are enforced while writing
+fn extract_tar<R: Read>(reader: R, extraction: &mut Extraction<'_>, unpacked_estimate: u64) -> Result<(), ArchiveError> {
+    extraction.check_space(unpacked_estimate)?;
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_path_buf();
+        let relative_path = enclosed_path(&name)?;
+        let entry_type = entry.header().entry_type();
+        let kind = if entry_type.is_dir() {
+            EntryKind::Directory
+        } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let target = entry.link_name()?.ok_or_else(|| ArchiveError::Corrupt(format!("{} is a link without a target", name.display())))?.to_path_buf();
+            if entry_type.is_symlink() {
+                EntryKind::Symlink(target)
+            } else {
+                EntryKind::HardLink(target)
+            }
+        } else if entry_type.is_file() || entry_type.is_contiguous() {
+            EntryKind::File
+        } else {
+            continue; // Devices, fifos and metadata-only entries
+        };
+        let mode = entry.header().mode().ok();
+        extraction.write_entry(&relative_path, kind, mode, &mut entry)?;
+    }
+    Ok(())
+}
+
+fn extract_7z(archive_path: &Path, extraction: &mut Extraction<'_>, progress: &Progress) -> Result<(), ArchiveError> {
+    let mut archive = SevenZReader::open(archive_path, Password::empty()).map_err(|e| ArchiveError::Corrupt(e.to_string()))?;
+    let files = &archive.archive().files;
+    let declared_size = files.iter().fold(0u64, |total, entry| total.saturating_add(entry.size()));
+    extraction.check_declared(Some(files.len()), declared_size)?;
+    progress.set_bytes_total(declared_size);
+    progress.set_files(0, files.iter().filter(|entry| !entry.is_directory()).count() as u64);
+
+    let mut result = Ok(());
+    archive.for_each_entries(|entry, reader| {
+        let attributes = entry.windows_attributes();
+        let mode = if attributes & SEVEN_ZIP_UNIX_EXTENSION != 0 { Some(attributes >> 16) } else { None };
+        let kind = if entry.is_directory() {
+            Ok(EntryKind::Directory)
+        } else if mode.is_some_and(|mode| mode & FILE_TYPE_MASK == SYMLINK_TYPE) {
+            read_link_target(reader).map(EntryKind::Symlink)
+        } else {
+            Ok(EntryKind::File)
+        };
+        result = enclosed_path(Path::new(entry.name()))
+            .and_then(|relative_path| kind.map(|kind| (relative_path, kind)))
+            .and_then(|(relative_path, kind)| extraction.write_entry(&relative_path, kind, mode, reader));
+        Ok(result.is_ok())
+    }).map_err(|e| ArchiveError::Corrupt(e.to_string()))?;
+    result
+}
+
+// Gzip ends with the unpacked size modulo 4 GiB, so it's a lower bound for huge files
+fn gzip_unpacked_size(archive_path: &Path) -> u64 {
+    let read_trailer = || -> io::Result<u64> {
+        let mut file = File::open(archive_path)?;
+        file.seek(SeekFrom::End(-4))?;
+        let mut trailer = [0; 4];
+        file.read_exact(&mut trailer)?;
+        Ok(u32::from_le_bytes(trailer) as u64)
+    };
+    read_trailer().unwrap_or(0)
+}
+
+// Zip and 7z store a symlink's target as the entry's contents
+fn read_link_target(reader: &mut dyn Read) -> Result<PathBuf, ArchiveError> {
+    let mut target = String::new();
+    reader.read_to_string(&mut target)?;
+    Ok(PathBuf::from(target))
+}
+
+// The same rules as zip's enclosed_name for formats that don't have one
+fn enclosed_path(name: &Path) -> Result<PathBuf, ArchiveError> {
+    resolve_inside(Path::new(""), name).ok_or_else(|| ArchiveError::UnsafePath(name.display().to_string()))
+}
+
+// Where target lands when followed from base, or None if it climbs out of the root
+fn resolve_inside(base: &Path, target: &Path) -> Option<PathBuf> {
+    let mut resolved: Vec<&std::ffi::OsStr> = base.iter().collect();
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved.iter().collect())
+}
+
+// Link targets may only climb with leading ..s, which start from the link's folder and so are real folders
+// A .. after a name could climb out of another link, Ex: y -> x/../secret with x -> ., whenever x is extracted
+fn is_normalized(target: &Path) -> bool {
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => descended = true,
+            Component::ParentDir if descended => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+// Writing through an extracted symlink could land anywhere, so entries may only go through real folders
+fn ensure_no_link_ancestors(destination: &Path, relative_path: &Path) -> Result<(), ArchiveError> {
+    let mut path = destination.to_path_buf();
+    if let Some(parent) = relative_path.parent() {
+        for part in parent.iter() {
+            path.push(part);
+            if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                return Err(ArchiveError::UnsafeLink(relative_path.display().to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn remove_existing(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+// Windows needs extra privileges for symlinks, so fall back to a copy when the target already exists
+#[cfg(not(unix))]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else if std::os::windows::fs::symlink_file(target, link).is_ok() {
+        Ok(())
+    } else if resolved.is_file() {
+        fs::copy(resolved, link).map(|_| ())
+    } else {
+        println!("Skipping symlink {}", link.display());
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        // Only permission bits; setuid and friends are never wanted from a download
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+// Reports how far through the underlying file a streaming decoder has read
+struct ProgressReader {
+    inner: BufReader<File>,
+    read: u64,
+    progress: Progress,
+}
+
+impl ProgressReader {
+    fn open(path: &Path, progress: &Progress) -> io::Result<Self> {
+        let file = File::open(path)?;
+        progress.set_bytes_total(file.metadata()?.len());
+        Ok(ProgressReader {
+            inner: BufReader::new(file),
+            read: 0,
+            progress: progress.clone(),
+        })
+    }
+}
+
+impl Read for ProgressReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        self.progress.set_bytes_done(self.read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use xz2::write::XzEncoder;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-{}-{}", name, fastrand::u64(..)));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn tar_bytes() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let contents = b"fn main() {}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "graalvm/bin/java", &contents[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn assert_extracted(archive_path: &Path, expected: ArchiveFormat) {
+        assert_eq!(detect_format(archive_path), Some(expected));
+        let destination = archive_path.parent().unwrap().join("out");
+        let progress = Progress::default();
+        extract_archive(archive_path, &destination, ExtractionLimits::default(), &progress).unwrap();
+        assert_eq!(fs::read_to_string(destination.join("graalvm/bin/java")).unwrap(), "fn main() {}");
+        assert_eq!((progress.phase(), progress.fraction(), progress.files_done()), (Phase::Extracting, Some(1.), 1));
+        if matches!(expected, ArchiveFormat::Zip | ArchiveFormat::SevenZip) {
+            assert_eq!(progress.files_total(), 1);
+        }
+        fs::remove_dir_all(archive_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn extracts_compressed_tarballs() {
+        let dir = scratch_dir("tar-gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar_bytes()).unwrap();
+        // The extension is deliberately wrong; the magic bytes decide
+        let path = dir.join("graalvm.tar");
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+        // Free space is checked against the unpacked size, not the download's
+        assert_eq!(gzip_unpacked_size(&path), tar_bytes().len() as u64);
+        assert_extracted(&path, ArchiveFormat::TarGz);
+
+        let dir = scratch_dir("tar-xz");
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&tar_bytes()).unwrap();
+        let path = dir.join("graalvm.tar.xz");
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+        assert_extracted(&path, ArchiveFormat::TarXz);
+
+        let dir = scratch_dir("tar");
+        let path = dir.join("graalvm.tar");
+        fs::write(&path, tar_bytes()).unwrap();
+        assert_extracted(&path, ArchiveFormat::Tar);
+    }
+
+    #[test]
+    fn extracts_zip_and_7z() {
+        let dir = scratch_dir("zip");
+        let path = dir.join("graalvm.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("graalvm/bin/java", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"fn main() {}").unwrap();
+        writer.finish().unwrap();
+        assert_extracted(&path, ArchiveFormat::Zip);
+
+        let dir = scratch_dir("7z");
+        let source = dir.join("source");
+        fs::create_dir_all(source.join("graalvm/bin")).unwrap();
+        fs::write(source.join("graalvm/bin/java"), "fn main() {}").unwrap();
+        let path = dir.join("graalvm.7z");
+        sevenz_rust::compress_to_path(&source, &path).unwrap();
+        assert_extracted(&path, ArchiveFormat::SevenZip);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restores_modes_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir("modes");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(12);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, "graalvm/bin/java", &b"fn main() {}"[..]).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "graalvm/bin/javaw", "java").unwrap();
+        let path = dir.join("graalvm.tar");
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+
+        let destination = dir.join("out");
+        extract_archive(&path, &destination, ExtractionLimits::default(), &Progress::default()).unwrap();
+        let java = destination.join("graalvm/bin/java");
+        assert_eq!(fs::metadata(&java).unwrap().permissions().mode() & 0o777, 0o755);
+        let javaw = destination.join("graalvm/bin/javaw");
+        assert_eq!(fs::read_link(&javaw).unwrap(), Path::new("java"));
+        assert_eq!(fs::read_to_string(&javaw).unwrap(), "fn main() {}");
+
+        let path = dir.join("graalvm.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("bin/launcher", SimpleFileOptions::default().unix_permissions(0o750)).unwrap();
+        writer.write_all(b"#!/bin/sh").unwrap();
+        writer.add_symlink("bin/launcher-link", "launcher", SimpleFileOptions::default()).unwrap();
+        writer.finish().unwrap();
+        let destination = dir.join("zip-out");
+        extract_archive(&path, &destination, ExtractionLimits::default(), &Progress::default()).unwrap();
+        assert_eq!(fs::metadata(destination.join("bin/launcher")).unwrap().permissions().mode() & 0o777, 0o750);
+        assert_eq!(fs::read_link(destination.join("bin/launcher-link")).unwrap(), Path::new("launcher"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_links_leaving_the_app_folder() {
+        let dir = scratch_dir("escape");
+        for (link, target) in [("graalvm/lib", "../../outside"), ("graalvm/abs", "/etc/passwd")] {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_cksum();
+            builder.append_link(&mut header, link, target).unwrap();
+            let path = dir.join("escape.tar");
+            fs::write(&path, builder.into_inner().unwrap()).unwrap();
+            let result = extract_archive(&path, &dir.join("out"), ExtractionLimits::default(), &Progress::default());
+            assert!(matches!(result, Err(ArchiveError::UnsafeLink(_))), "{} -> {}", link, target);
+        }
+
+        // A link that stays inside is fine, but nothing may be written through it
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "graalvm/current", ".").unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_cksum();
+        builder.append_data(&mut header, "graalvm/current/file", &b"x"[..]).unwrap();
+        let path = dir.join("through.tar");
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        let result = extract_archive(&path, &dir.join("through"), ExtractionLimits::default(), &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::UnsafeLink(_))));
+
+        // Each link looks safe on its own, but y follows x back out of the folder
+        for links in [[("x", "."), ("y", "x/../secret")], [("y", "x/../secret"), ("x", ".")]] {
+            let mut builder = tar::Builder::new(Vec::new());
+            for (link, target) in links {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                header.set_cksum();
+                builder.append_link(&mut header, link, target).unwrap();
+            }
+            let path = dir.join("chain.tar");
+            fs::write(&path, builder.into_inner().unwrap()).unwrap();
+            let destination = dir.join(format!("chain-{}", links[0].0));
+            let result = extract_archive(&path, &destination, ExtractionLimits::default(), &Progress::default());
+            assert!(matches!(result, Err(ArchiveError::UnsafeLink(_))), "{:?}", links);
+        }
+
+        // Hard links are copies, which would read whatever a symlink points at
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "x", ".").unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "copy", "x").unwrap();
+        let path = dir.join("hard.tar");
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        let result = extract_archive(&path, &dir.join("hard"), ExtractionLimits::default(), &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::UnsafeLink(_))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_escaping_paths_and_bombs() {
+        let dir = scratch_dir("limits");
+        let path = dir.join("slip.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("../../evil.sh", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"#!/bin/sh").unwrap();
+        writer.finish().unwrap();
+        let result = extract_archive(&path, &dir.join("slip"), ExtractionLimits::default(), &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::UnsafePath(_))));
+        assert!(!dir.join("evil.sh").exists());
+
+        // 16 MB of zeros squeezes into a few KB
+        let path = dir.join("bomb.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("zeros", SimpleFileOptions::default()).unwrap();
+        writer.write_all(&vec![0; 16 * 1024 * 1024]).unwrap();
+        writer.finish().unwrap();
+        let result = extract_archive(&path, &dir.join("bomb"), ExtractionLimits::default(), &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::SuspiciousRatio(100))));
+
+        let limits = ExtractionLimits { max_entries: 1, ..ExtractionLimits::default() };
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["a", "b"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        let path = dir.join("many.tar");
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        let result = extract_archive(&path, &dir.join("many"), limits, &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::TooManyEntries(1))));
+
+        let limits = ExtractionLimits { max_total_size: 4, ..ExtractionLimits::default() };
+        let path = dir.join("graalvm.tar");
+        fs::write(&path, tar_bytes()).unwrap();
+        let result = extract_archive(&path, &dir.join("large"), limits, &Progress::default());
+        assert!(matches!(result, Err(ArchiveError::TooLarge(4))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_files_are_not_archives() {
+        assert_eq!(format_from_magic(b"#!/bin/sh"), None);
+        assert!(!is_archive_name("open_lights_core-x86_64-unknown-linux-gnu"));
+        assert!(is_archive_name("graalvm-community-jdk-21_linux-x64_bin.tar.gz"));
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::gui::AssetData;
+
+// Checksums and signatures published next to the real downloads
+const SIDECAR_PATTERN: &str = r"(?i)(\.(sha256|sha512|md5|sig|minisig|asc)$|^sha256sums)";
+
+// Which release asset to install, Ex: { "include": ["linux"], "exclude": ["debug"], "prefer": ["\\.tar\\.xz$", "\\.tar\\.gz$"] }
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct AssetRules {
+    #[serde(default)]
+    pub include: Vec<String>, // Every one of these has to match the file name
+    #[serde(default)]
+    pub exclude: Vec<String>, // Any one of these rules the file out
+    #[serde(default)]
+    pub prefer: Vec<String>, // Earlier patterns score higher; the best scoring file is picked
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetMatchError {
+    NoMatch(Vec<String>), // Every asset in the release
+    Ambiguous(Vec<String>), // The assets that tied for the best score
+    InvalidPattern(String),
+}
+
+impl fmt::Display for AssetMatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetMatchError::NoMatch(candidates) if candidates.is_empty() => write!(f, "The release has no files"),
+            AssetMatchError::NoMatch(candidates) => write!(f, "No file matches this device. Available: {}", candidates.join(", ")),
+            AssetMatchError::Ambiguous(candidates) => write!(f, "Several files could be installed: {}", candidates.join(", ")),
+            AssetMatchError::InvalidPattern(error) => write!(f, "The app has an invalid asset pattern: {}", error),
+        }
+    }
+}
+
+pub fn asset_filename(asset: &AssetData) -> &str {
+    asset.browser_download_url.split('/').next_back().unwrap_or("unknown")
+}
+
+pub fn select_asset<'a>(assets: &'a [AssetData], rules: &AssetRules) -> Result<&'a AssetData, AssetMatchError> {
+    let include = compile(&rules.include)?;
+    let mut exclude = compile(&rules.exclude)?;
+    exclude.push(Regex::new(SIDECAR_PATTERN).unwrap());
+    let prefer = compile(&rules.prefer)?;
+
+    let mut best_score = 0;
+    let mut best: Vec<&AssetData> = Vec::new();
+    for asset in assets {
+        let filename = asset_filename(asset);
+        if !include.iter().all(|pattern| pattern.is_match(filename)) || exclude.iter().any(|pattern| pattern.is_match(filename)) {
+            println!("Skipping {}", filename);
+            continue;
+        }
+
+        let score = prefer.iter()
+            .position(|pattern| pattern.is_match(filename))
+            .map_or(0, |index| prefer.len() - index);
+        println!("Candidate {} scored {}", filename, score);
+        if best.is_empty() || score > best_score {
+            best_score = score;
+            best = vec![asset];
+        } else if score == best_score {
+            best.push(asset);
+        }
+    }
+
+    match best.as_slice() {
+        [asset] => Ok(asset),
+        [] => Err(AssetMatchError::NoMatch(assets.iter().map(|asset| asset_filename(asset).to_string()).collect())),
+        tied => Err(AssetMatchError::Ambiguous(tied.iter().map(|asset| asset_filename(asset).to_string()).collect())),
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>, AssetMatchError> {
+    patterns.iter()
+        .map(|pattern| Regex::new(pattern).map_err(|e| AssetMatchError::InvalidPattern(e.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets(filenames: &[&str]) -> Vec<AssetData> {
+        filenames.iter()
+            .map(|filename| AssetData {
+                size: 0,
+                browser_download_url: format!("https://github.com/Open-Lights/OpenLightsCore/releases/download/v1.0.0/{}", filename),
+                digest: None,
+            })
+            .collect()
+    }
+
+    fn rules(include: &[&str], exclude: &[&str], prefer: &[&str]) -> AssetRules {
+        let to_strings = |patterns: &[&str]| patterns.iter().map(|pattern| pattern.to_string()).collect();
+        AssetRules {
+            include: to_strings(include),
+            exclude: to_strings(exclude),
+            prefer: to_strings(prefer),
+        }
+    }
+
+    #[test]
+    fn skips_sidecars_and_excluded_builds() {
+        let assets = assets(&["open_lights_core-x86_64-pc-windows-msvc.exe", "open_lights_core-x86_64-pc-windows-msvc.exe.sha256", "open_lights_core-x86_64-pc-windows-msvc-debug.exe", "SHA256SUMS"]);
+        let selected = select_asset(&assets, &rules(&["x86_64-pc-windows-msvc"], &["debug"], &[])).unwrap();
+        assert_eq!(asset_filename(selected), "open_lights_core-x86_64-pc-windows-msvc.exe");
+    }
+
+    #[test]
+    fn preferred_patterns_break_ties() {
+        let assets = assets(&["graalvm-jdk-21_linux-x64_bin.zip", "graalvm-jdk-21_linux-x64_bin.tar.gz", "graalvm-jdk-21_linux-x64_bin.tar.xz"]);
+        let selected = select_asset(&assets, &rules(&["linux-x64"], &[], &[r"\.tar\.xz$", r"\.tar\.gz$"])).unwrap();
+        assert_eq!(asset_filename(selected), "graalvm-jdk-21_linux-x64_bin.tar.xz");
+
+        let error = select_asset(&assets, &rules(&["linux-x64"], &[], &[])).unwrap_err();
+        assert_eq!(error, AssetMatchError::Ambiguous(vec![
+            "graalvm-jdk-21_linux-x64_bin.zip".to_string(),
+            "graalvm-jdk-21_linux-x64_bin.tar.gz".to_string(),
+            "graalvm-jdk-21_linux-x64_bin.tar.xz".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn reports_every_asset_when_nothing_matches() {
+        let assets = assets(&["BeatMaker.jar", "BeatMaker.jar.sha256"]);
+        let error = select_asset(&assets, &rules(&["linux"], &[], &[])).unwrap_err();
+        assert_eq!(error, AssetMatchError::NoMatch(vec!["BeatMaker.jar".to_string(), "BeatMaker.jar.sha256".to_string()]));
+        assert!(matches!(select_asset(&assets, &rules(&["("], &[], &[])), Err(AssetMatchError::InvalidPattern(_))));
+    }
+}
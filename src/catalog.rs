@@ -0,0 +1,95 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::io::InstallationData;
+use crate::source::SourceConfig;
+
+// Bump this whenever the catalog format changes in a way older managers can't read
+pub const CATALOG_VERSION: u32 = 1;
+
+const BUNDLED_CATALOG_PATH: &str = "assets/catalog.json";
+const USER_CATALOG_PATH: &str = "openlightsmanager/catalog.json";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Catalog {
+    pub version: u32,
+    pub apps: Vec<CatalogEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CatalogEntry {
+    pub repo: String, // Ex: Open-Lights/OpenLightsCore
+    pub display_name: String,
+    pub icon: String, // Relative to the assets folder unless absolute
+    pub installation_data: InstallationData,
+    #[serde(default)]
+    pub source: SourceConfig, // Github unless stated otherwise
+}
+
+impl CatalogEntry {
+    // The repository name doubles as the app's id for appdata and install folders
+    pub fn name(&self) -> &str {
+        self.repo.split('/').nth(1).unwrap_or("")
+    }
+
+    pub fn icon_path(&self) -> PathBuf {
+        let icon = Path::new(&self.icon);
+        if icon.is_absolute() {
+            icon.to_path_buf()
+        } else {
+            Path::new("assets").join(icon)
+        }
+    }
+}
+
+impl Catalog {
+    pub fn get(&self, name: &str) -> Option<&CatalogEntry> {
+        self.apps.iter().find(|entry| entry.name() == name)
+    }
+
+    // User entries replace bundled entries for the same repo name; new ones are appended
+    fn merge(&mut self, other: Catalog) {
+        for entry in other.apps {
+            if let Some(existing) = self.apps.iter_mut().find(|existing| existing.name() == entry.name()) {
+                *existing = entry;
+            } else {
+                self.apps.push(entry);
+            }
+        }
+    }
+}
+
+pub fn load_catalog() -> Catalog {
+    // Still starts without it, so a user catalog alone can list apps
+    let mut catalog = read_catalog(Path::new(BUNDLED_CATALOG_PATH)).unwrap_or_else(|| {
+        println!("The bundled app catalog is missing or invalid");
+        Catalog { version: CATALOG_VERSION, apps: Vec::new() }
+    });
+
+    let user_path = Path::new(USER_CATALOG_PATH);
+    if user_path.exists() {
+        if let Some(user_catalog) = read_catalog(user_path) {
+            catalog.merge(user_catalog);
+        }
+    }
+    catalog
+}
+
+fn read_catalog(path: &Path) -> Option<Catalog> {
+    let file = File::open(path).ok()?;
+    let reader = BufReader::new(file);
+    match serde_json::from_reader::<_, Catalog>(reader) {
+        Ok(catalog) if catalog.version <= CATALOG_VERSION => Some(catalog),
+        Ok(catalog) => {
+            println!("Ignoring {}: catalog version {} is newer than supported version {}", path.display(), catalog.version, CATALOG_VERSION);
+            None
+        }
+        Err(e) => {
+            println!("Ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
+}
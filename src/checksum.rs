@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::asset_match::asset_filename;
+use crate::gui::{AssetData, ReleaseData};
+use crate::io::{check_status, github_request, local_file_path, GithubError};
+use crate::rate_limit::RateLimit;
+
+// What is known about the installed file, saved with the app
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "sha256", rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    #[default]
+    Unknown, // Not installed through a checked download yet
+    Unpublished, // The release offered nothing to check against
+    Verified(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChecksumError {
+    Mismatch { expected: String, actual: String },
+    Fetch(GithubError),
+    Malformed(String), // The checksum file that was published can't be read
+    NotListed(String), // The sums file leaves the asset out
+}
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumError::Mismatch { expected, actual } => write!(f, "The download is corrupted or was tampered with.\nExpected SHA-256 {}, got {}", expected, actual),
+            ChecksumError::Fetch(error) => write!(f, "Failed to fetch the checksum: {}", error),
+            ChecksumError::Malformed(file) => write!(f, "{} doesn't hold a SHA-256", file),
+            ChecksumError::NotListed(file) => write!(f, "The release's checksums don't list {}", file),
+        }
+    }
+}
+
+// Takes each chunk of a download as it's written to disk
+#[derive(Default)]
+pub struct Sha256Hasher {
+    hasher: Sha256,
+    len: u64, // Bytes hashed so far
+}
+
+impl Sha256Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+        self.len += data.len() as u64;
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn reset(&mut self) {
+        *self = Sha256Hasher::default();
+    }
+
+    pub fn update_from_reader(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.update(&buffer[..read]);
+        }
+    }
+
+    pub fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256Hasher::default();
+    hasher.update_from_reader(File::open(path)?)?;
+    Ok(hasher.finish())
+}
+
+// The digest the asset should have, checked in order: the app descriptor, the asset's own digest field, a {file}.sha256 sidecar, then SHA256SUMS
+pub async fn expected_sha256(release_data: &ReleaseData, asset: &AssetData, pinned: &HashMap<String, String>, token: &str) -> Result<Option<String>, ChecksumError> {
+    let filename = asset_filename(asset);
+    if let Some(digest) = pinned.get(filename) {
+        return Ok(Some(digest.to_ascii_lowercase()));
+    }
+    if let Some(digest) = asset.digest.as_deref().and_then(|digest| digest.strip_prefix("sha256:")) {
+        return Ok(Some(digest.to_ascii_lowercase()));
+    }
+
+    let sidecar_name = format!("{}.sha256", filename).to_ascii_lowercase();
+    if let Some(sidecar) = release_data.assets.iter().find(|other| asset_filename(other).to_ascii_lowercase() == sidecar_name) {
+        let contents = fetch_text(&sidecar.browser_download_url, token).await.map_err(ChecksumError::Fetch)?;
+        return parse_sha256_line(&contents, None).map(Some).ok_or_else(|| ChecksumError::Malformed(asset_filename(sidecar).to_string()));
+    }
+
+    if let Some(sums) = release_data.assets.iter().find(|other| is_sums_file(asset_filename(other))) {
+        let contents = fetch_text(&sums.browser_download_url, token).await.map_err(ChecksumError::Fetch)?;
+        // Once checksums are published, an asset without one can't be trusted
+        return contents.lines()
+            .find_map(|line| parse_sha256_line(line, Some(filename)))
+            .map(Some)
+            .ok_or_else(|| ChecksumError::NotListed(filename.to_string()));
+    }
+    Ok(None)
+}
+
+pub fn check_sha256(expected: Option<String>, actual: String) -> Result<ChecksumStatus, ChecksumError> {
+    match expected {
+        Some(expected) if expected == actual => Ok(ChecksumStatus::Verified(actual)),
+        Some(expected) => Err(ChecksumError::Mismatch { expected, actual }),
+        None => Ok(ChecksumStatus::Unpublished),
+    }
+}
+
+fn is_sums_file(filename: &str) -> bool {
+    let filename = filename.to_ascii_lowercase();
+    filename == "sha256sums" || filename == "sha256sums.txt"
+}
+
+// Reads "<hex>  <file>" as written by sha256sum; a sidecar may hold only the hex
+fn parse_sha256_line(line: &str, filename: Option<&str>) -> Option<String> {
+    let mut parts = line.split_whitespace();
+    let digest = parts.next()?;
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    if let Some(filename) = filename {
+        // A leading * marks binary mode
+        let listed = parts.next()?.trim_start_matches('*');
+        if listed != filename && !listed.ends_with(&format!("/{}", filename)) {
+            return None;
+        }
+    }
+    Some(digest.to_ascii_lowercase())
+}
+
+// Small text assets like checksum and signature files
+pub(crate) async fn fetch_text(url: &str, token: &str) -> Result<String, GithubError> {
+    if let Some(path) = local_file_path(url)? {
+        return std::fs::read_to_string(path).map_err(|e| GithubError::Io(e.to_string()));
+    }
+    let client = reqwest::Client::new();
+    let response = github_request(&client, url, token)
+        .send()
+        .await
+        .map_err(|e| GithubError::Network(e.to_string()))?;
+    check_status(&response, token, &mut RateLimit::default())?;
+    response.text().await.map_err(|e| GithubError::Network(e.to_string()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn asset(filename: &str, digest: Option<&str>) -> AssetData {
+        AssetData {
+            size: 5,
+            browser_download_url: format!("file://{}", filename),
+            digest: digest.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn hashes_while_streaming() {
+        let mut hasher = Sha256Hasher::default();
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+        assert_eq!(hasher.finish(), HELLO_SHA256);
+    }
+
+    #[test]
+    fn reads_sidecars_and_sums_files() {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-checksum-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        let app = dir.join("BeatMaker.jar");
+        let sums = dir.join("SHA256SUMS");
+        std::fs::write(&sums, format!("{}  OpenLightsCore.exe\n{} *BeatMaker.jar\n", "0".repeat(64), HELLO_SHA256.to_uppercase())).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let release_data = ReleaseData {
+            tag_name: "v1.0.0".to_string(),
+            prerelease: false,
+            draft: false,
+            id: 1,
+            assets: vec![asset(&app.to_string_lossy(), None), asset(&sums.to_string_lossy(), None)],
+            ..ReleaseData::default()
+        };
+        let expected = rt.block_on(expected_sha256(&release_data, &release_data.assets[0], &HashMap::new(), "")).unwrap();
+        assert_eq!(expected.as_deref(), Some(HELLO_SHA256));
+
+        let sidecar = dir.join("BeatMaker.jar.sha256");
+        std::fs::write(&sidecar, format!("{}\n", "a".repeat(64))).unwrap();
+        let mut release_data = release_data;
+        release_data.assets.push(asset(&sidecar.to_string_lossy(), None));
+        let expected = rt.block_on(expected_sha256(&release_data, &release_data.assets[0], &HashMap::new(), "")).unwrap();
+        assert_eq!(expected, Some("a".repeat(64)));
+
+        // A digest on the asset itself needs no extra download
+        release_data.assets[0].digest = Some(format!("sha256:{}", HELLO_SHA256));
+        let expected = rt.block_on(expected_sha256(&release_data, &release_data.assets[0], &HashMap::new(), "")).unwrap();
+        assert_eq!(expected.as_deref(), Some(HELLO_SHA256));
+
+        // The catalog's digest beats everything the release publishes
+        let pinned = HashMap::from([("BeatMaker.jar".to_string(), "B".repeat(64))]);
+        let expected = rt.block_on(expected_sha256(&release_data, &release_data.assets[0], &pinned, "")).unwrap();
+        assert_eq!(expected, Some("b".repeat(64)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_sidecars_are_refused() {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-checksum-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        let app = dir.join("BeatMaker.jar");
+        let sidecar = dir.join("BeatMaker.jar.sha256");
+        std::fs::write(&sidecar, "<html>Not Found</html>\n").unwrap();
+        let release_data = ReleaseData {
+            assets: vec![asset(&app.to_string_lossy(), None), asset(&sidecar.to_string_lossy(), None)],
+            ..ReleaseData::default()
+        };
+
+        let result = Runtime::new().unwrap().block_on(expected_sha256(&release_data, &release_data.assets[0], &HashMap::new(), ""));
+        assert!(matches!(result, Err(ChecksumError::Malformed(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sums_files_must_list_the_asset() {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-checksum-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        let app = dir.join("BeatMaker.jar");
+        let sums = dir.join("SHA256SUMS.txt");
+        std::fs::write(&sums, format!("{}  OpenLightsCore.exe\n", "0".repeat(64))).unwrap();
+        let release_data = ReleaseData {
+            assets: vec![asset(&app.to_string_lossy(), None), asset(&sums.to_string_lossy(), None)],
+            ..ReleaseData::default()
+        };
+
+        let result = Runtime::new().unwrap().block_on(expected_sha256(&release_data, &release_data.assets[0], &HashMap::new(), ""));
+        assert_eq!(result, Err(ChecksumError::NotListed("BeatMaker.jar".to_string())));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn mismatches_are_refused() {
+        assert_eq!(check_sha256(Some(HELLO_SHA256.to_string()), HELLO_SHA256.to_string()), Ok(ChecksumStatus::Verified(HELLO_SHA256.to_string())));
+        assert_eq!(check_sha256(None, HELLO_SHA256.to_string()), Ok(ChecksumStatus::Unpublished));
+        assert!(matches!(check_sha256(Some("0".repeat(64)), HELLO_SHA256.to_string()), Err(ChecksumError::Mismatch { .. })));
+    }
+}
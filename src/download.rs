@@ -0,0 +1,346 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::{header, StatusCode};
+
+use crate::checksum::Sha256Hasher;
+use crate::io::{check_status, github_request, local_file_path, GithubError};
+use crate::progress::{Phase, Progress};
+use crate::rate_limit::RateLimit;
+
+const RUNNING: u8 = 0;
+const PAUSED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+// Shared between an app card and its download thread so the user can pause or stop it
+#[derive(Debug, Clone, Default)]
+pub struct DownloadControl {
+    state: Arc<AtomicU8>,
+}
+
+impl DownloadControl {
+    pub fn pause(&self) {
+        // A cancelled download stays cancelled
+        let _ = self.state.compare_exchange(RUNNING, PAUSED, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.state.compare_exchange(PAUSED, RUNNING, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    pub fn cancel(&self) {
+        self.state.store(CANCELLED, Ordering::Relaxed);
+    }
+
+    // Called by the queue when the next job for the app starts
+    pub fn reset(&self) {
+        self.state.store(RUNNING, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == PAUSED
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == CANCELLED
+    }
+
+    fn is_running(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == RUNNING
+    }
+
+    // Waits out a pause or the given delay, whichever is longer; stops early when cancelled
+    async fn wait(&self, delay: Duration) {
+        let step = Duration::from_millis(100);
+        let mut waited = Duration::ZERO;
+        while !self.is_cancelled() && (waited < delay || self.is_paused()) {
+            tokio::time::sleep(step).await;
+            waited += step;
+        }
+    }
+}
+
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Downloads to {path}.part first so an interrupted download can pick up where it stopped
+// expected_size is 0 when the release didn't say; returns the SHA-256 of the whole file
+// Cancelling removes the partial file
+pub async fn get_file(url: &str, token: &str, path: &str, expected_size: u64, progress: &Progress, control: &DownloadControl) -> Result<String, GithubError> {
+    let part_path = format!("{}.part", path);
+    progress.start_phase(Phase::Downloading, expected_size);
+
+    let mut hasher = Sha256Hasher::default();
+    // Release indexes may point at files on a mounted share
+    if let Some(source_path) = local_file_path(url)? {
+        copy_file(&source_path, &part_path, &mut hasher, progress).map_err(|e| GithubError::Io(e.to_string()))?;
+    } else {
+        let mut attempt = 0;
+        loop {
+            control.wait(Duration::ZERO).await;
+            if control.is_cancelled() {
+                let _ = fs::remove_file(&part_path);
+                return Err(GithubError::Cancelled);
+            }
+            match download_part(url, token, &part_path, expected_size, progress, control, &mut hasher).await {
+                Ok(()) => break,
+                // Pausing drops the connection; resuming continues from the .part file
+                Err(GithubError::Cancelled) => continue,
+                Err(error) if attempt + 1 < MAX_ATTEMPTS && is_transient(&error) => {
+                    attempt += 1;
+                    let delay = retry_delay(attempt);
+                    println!("Download of {} failed ({}), retrying in {}s", url, error, delay.as_secs());
+                    control.wait(delay).await;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    let size = part_size(&part_path);
+    if expected_size > 0 && size != expected_size {
+        let _ = fs::remove_file(&part_path);
+        return Err(GithubError::Incomplete { expected: expected_size, actual: size });
+    }
+    fs::rename(&part_path, path).map_err(|e| GithubError::Io(e.to_string()))?;
+    Ok(hasher.finish())
+}
+
+// Copies a local file into the .part file, hashing it on the way
+fn copy_file(source_path: &Path, part_path: &str, hasher: &mut Sha256Hasher, progress: &Progress) -> std::io::Result<()> {
+    let mut source = File::open(source_path)?;
+    let mut file = File::create(part_path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        file.write_all(&buffer[..read])?;
+        hasher.update(&buffer[..read]);
+        progress.set_bytes_done(hasher.len());
+    }
+}
+
+// The hasher has to have seen exactly the bytes before start
+fn catch_up_hasher(hasher: &mut Sha256Hasher, part_path: &str, start: u64) -> Result<(), GithubError> {
+    if hasher.len() == start {
+        return Ok(());
+    }
+    // Only a .part file left from an earlier run, or a chunk cut short, is read back
+    hasher.reset();
+    if start > 0 {
+        let file = File::open(part_path).map_err(|e| GithubError::Io(e.to_string()))?;
+        hasher.update_from_reader(file.take(start)).map_err(|e| GithubError::Io(e.to_string()))?;
+    }
+    Ok(())
+}
+
+// One request, continuing from whatever is already in the .part file
+async fn download_part(url: &str, token: &str, part_path: &str, expected_size: u64, progress: &Progress, control: &DownloadControl, hasher: &mut Sha256Hasher) -> Result<(), GithubError> {
+    let mut start = part_size(part_path);
+    if expected_size > 0 && start > expected_size {
+        // Left over from a different file with the same name
+        start = 0;
+    }
+    if expected_size > 0 && start == expected_size {
+        return catch_up_hasher(hasher, part_path, start);
+    }
+
+    let client = reqwest::Client::new();
+    // reqwest drops the Authorization header when Github redirects to its asset CDN
+    let mut request = github_request(&client, url, token);
+    if start > 0 {
+        println!("Resuming {} from byte {}", url, start);
+        request = request.header(header::RANGE, format!("bytes={}-", start));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| GithubError::Network(e.to_string()))?;
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The server disagrees about what's left, so start over on the next attempt
+        let _ = fs::remove_file(part_path);
+        return Err(GithubError::Http(response.status().as_u16()));
+    }
+    // Asset downloads don't count against the API rate limit
+    check_status(&response, token, &mut RateLimit::default())?;
+
+    // Servers that ignore Range send the whole file again
+    if response.status() != StatusCode::PARTIAL_CONTENT || content_range_start(&response) != Some(start) {
+        start = 0;
+    }
+    catch_up_hasher(hasher, part_path, start)?;
+    // Left unknown when the server omits Content-Length
+    let total = if expected_size > 0 {
+        expected_size
+    } else {
+        response.content_length().map_or(0, |length| start + length)
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(start > 0)
+        .truncate(start == 0)
+        .open(part_path)
+        .map_err(|e| GithubError::Io(e.to_string()))?;
+
+    let mut total_bytes_read = start;
+    progress.begin(start, total);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        if !control.is_running() {
+            return Err(GithubError::Cancelled);
+        }
+        let chunk = chunk.map_err(|e| GithubError::Network(e.to_string()))?;
+        file.write_all(&chunk).map_err(|e| GithubError::Io(e.to_string()))?;
+        hasher.update(&chunk);
+        total_bytes_read += chunk.len() as u64;
+        progress.set_bytes_done(total_bytes_read);
+    }
+
+    // The connection can close cleanly before everything arrived
+    if expected_size > 0 && total_bytes_read < expected_size {
+        return Err(GithubError::Incomplete { expected: expected_size, actual: total_bytes_read });
+    }
+    Ok(())
+}
+
+// Ex: "bytes 1000-1999/2000" starts at 1000
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let content_range = response.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    content_range.strip_prefix("bytes ")?.split('-').next()?.parse().ok()
+}
+
+fn part_size(part_path: &str) -> u64 {
+    fs::metadata(part_path).map_or(0, |metadata| metadata.len())
+}
+
+// Problems that might go away by asking again; refusals like 401 and 404 won't
+fn is_transient(error: &GithubError) -> bool {
+    match error {
+        GithubError::Network(_) | GithubError::Incomplete { .. } => true,
+        GithubError::Http(status) => *status >= 500 || *status == 408 || *status == 416,
+        _ => false,
+    }
+}
+
+// 1s, 2s, 4s... up to 30s
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY.saturating_mul(2u32.saturating_pow(attempt - 1)).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::thread;
+
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        assert!(is_transient(&GithubError::Http(503)));
+        assert!(!is_transient(&GithubError::Http(404)));
+        assert!(!is_transient(&GithubError::Unauthorized(401)));
+        // A full disk stays full
+        assert!(!is_transient(&GithubError::Io("No space left on device".to_string())));
+    }
+
+    #[test]
+    fn cancelling_removes_the_partial_file() {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-download-{}", fastrand::u64(..)));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BeatMaker.jar").to_string_lossy().to_string();
+        fs::write(format!("{}.part", path), "hel").unwrap();
+        let control = DownloadControl::default();
+        control.pause();
+        control.resume();
+        assert!(!control.is_paused());
+        control.cancel();
+        control.pause();
+        assert!(control.is_cancelled());
+
+        // Cancelled before connecting, so the address is never used
+        let result = Runtime::new().unwrap().block_on(get_file("http://127.0.0.1:9/BeatMaker.jar", "", &path, 5, &Progress::default(), &control));
+        assert_eq!(result, Err(GithubError::Cancelled));
+        assert!(!Path::new(&format!("{}.part", path)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resumes_after_the_connection_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/BeatMaker.jar", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut ranges = Vec::new();
+            for body in [&b"hel"[..], &b"lo"[..]] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut range = None;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: ") {
+                        range = Some(value.to_string());
+                    }
+                }
+                // The first response promises the whole file but hangs up early
+                let head = match &range {
+                    Some(_) => "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 3-4/5\r\nContent-Length: 2\r\n\r\n".to_string(),
+                    None => "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n".to_string(),
+                };
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+                ranges.push(range);
+            }
+            ranges
+        });
+
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-download-{}", fastrand::u64(..)));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BeatMaker.jar").to_string_lossy().to_string();
+        let progress = Progress::default();
+        let sha256 = Runtime::new().unwrap().block_on(get_file(&url, "", &path, 5, &progress, &DownloadControl::default())).unwrap();
+
+        assert_eq!(server.join().unwrap(), vec![None, Some("bytes=3-".to_string())]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+        assert_eq!((progress.bytes_done(), progress.bytes_total()), (5, 5));
+        assert_eq!(sha256, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert!(!Path::new(&format!("{}.part", path)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_files_of_the_wrong_size() {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-download-{}", fastrand::u64(..)));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.jar");
+        fs::write(&source, "hello").unwrap();
+        let path = dir.join("BeatMaker.jar").to_string_lossy().to_string();
+        let url = format!("file://{}", source.to_string_lossy());
+        let progress = Progress::default();
+        let rt = Runtime::new().unwrap();
+
+        assert_eq!(rt.block_on(get_file(&url, "", &path, 6, &progress, &DownloadControl::default())), Err(GithubError::Incomplete { expected: 6, actual: 5 }));
+        assert!(!Path::new(&path).exists());
+        assert!(rt.block_on(get_file(&url, "", &path, 5, &progress, &DownloadControl::default())).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+
+use tokio::runtime::Handle;
+
+use crate::download::DownloadControl;
+use crate::io::AppEvents;
+use crate::progress::Progress;
+
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    Install,
+    Update,
+    Version, // A specific release installed next to the active one
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Active,
+    Finished,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_done(&self) -> bool {
+        matches!(self, JobStatus::Finished | JobStatus::Failed(_) | JobStatus::Cancelled)
+    }
+}
+
+// What the Downloads screen shows for one install or update
+#[derive(Debug, Clone)]
+pub struct DownloadJob {
+    pub id: u64,
+    pub app_name: String,
+    pub display_name: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub control: DownloadControl,
+    pub progress: Progress,
+}
+
+// Runs on its own thread once the queue has room for it
+pub type JobWork = Box<dyn FnOnce(&DownloadControl, &Progress) -> JobStatus + Send>;
+
+// The app's events, for jobs that end without their work reporting it
+pub type JobEvents = Arc<Sender<(AppEvents, Option<String>)>>;
+
+struct QueueState {
+    jobs: Vec<DownloadJob>,
+    pending: VecDeque<(u64, JobWork, JobEvents, bool)>, // The bool is whether the control was left paused or cancelled by an earlier job
+    active: usize,
+    max_concurrent: usize,
+    next_id: u64,
+}
+
+// Every install and update goes through here so only a few download at once
+#[derive(Clone)]
+pub struct DownloadQueue {
+    state: Arc<Mutex<QueueState>>,
+    runtime: Handle, // Jobs run on the runtime's blocking threads
+}
+
+impl DownloadQueue {
+    pub fn new(max_concurrent: usize, runtime: Handle) -> Self {
+        DownloadQueue {
+            state: Arc::new(Mutex::new(QueueState {
+                jobs: Vec::new(),
+                pending: VecDeque::new(),
+                active: 0,
+                max_concurrent: max_concurrent.max(1),
+                next_id: 0,
+            })),
+            runtime,
+        }
+    }
+
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.state.lock().unwrap().max_concurrent = max_concurrent.max(1);
+        self.start_next();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn enqueue(&self, app_name: &str, display_name: &str, kind: JobKind, control: DownloadControl, progress: Progress, events: JobEvents, work: JobWork) -> u64 {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            // A retry replaces the app's earlier attempt
+            state.jobs.retain(|job| job.app_name != app_name || !job.status.is_done());
+            // A new job can't have been paused or cancelled yet, so that state belongs to an earlier one
+            let stale = control.is_paused() || control.is_cancelled();
+            state.jobs.push(DownloadJob {
+                id,
+                app_name: app_name.to_string(),
+                display_name: display_name.to_string(),
+                kind,
+                status: JobStatus::Queued,
+                control,
+                progress,
+            });
+            state.pending.push_back((id, work, events, stale));
+            id
+        };
+        self.start_next();
+        id
+    }
+
+    // A snapshot for drawing; the progress inside still updates live
+    pub fn jobs(&self) -> Vec<DownloadJob> {
+        self.state.lock().unwrap().jobs.clone()
+    }
+
+    pub fn job_for(&self, app_name: &str) -> Option<DownloadJob> {
+        self.state.lock().unwrap().jobs.iter().rev().find(|job| job.app_name == app_name).cloned()
+    }
+
+    pub fn clear_done(&self) {
+        self.state.lock().unwrap().jobs.retain(|job| !job.status.is_done());
+    }
+
+    fn start_next(&self) {
+        let mut state = self.state.lock().unwrap();
+        while state.active < state.max_concurrent {
+            let Some((id, work, events, stale)) = state.pending.pop_front() else {
+                break;
+            };
+            let Some(index) = state.jobs.iter().position(|job| job.id == id) else {
+                continue;
+            };
+            // The app's jobs share a control, so one still running keeps its pause or cancel
+            let app_name = &state.jobs[index].app_name;
+            let app_busy = state.jobs.iter().any(|job| job.app_name == *app_name && job.status == JobStatus::Active);
+            let job = &mut state.jobs[index];
+            if stale && !app_busy {
+                job.control.reset();
+            }
+            job.status = JobStatus::Active;
+            let control = job.control.clone();
+            let progress = job.progress.clone();
+            state.active += 1;
+
+            let queue = self.clone();
+            self.runtime.spawn_blocking(move || {
+                // Cancelled while still waiting in line
+                let status = if control.is_cancelled() {
+                    let _ = events.send((AppEvents::Cancelled, None));
+                    JobStatus::Cancelled
+                } else {
+                    // A panicking job still gives up its place in the queue
+                    catch_unwind(AssertUnwindSafe(|| work(&control, &progress))).unwrap_or_else(|_| {
+                        let reason = "The download stopped unexpectedly".to_string();
+                        let _ = events.send((AppEvents::Failed, Some(reason.clone())));
+                        progress.finish();
+                        JobStatus::Failed(reason)
+                    })
+                };
+                queue.finish(id, status);
+            });
+        }
+    }
+
+    fn finish(&self, id: u64, status: JobStatus) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.active -= 1;
+            if let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) {
+                job.status = status;
+            }
+        }
+        self.start_next();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    fn wait_until(queue: &DownloadQueue, done: impl Fn(&[DownloadJob]) -> bool) {
+        for _ in 0..200 {
+            if done(&queue.jobs()) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Timed out waiting for the queue: {:?}", queue.jobs());
+    }
+
+    fn app_events() -> (JobEvents, mpsc::Receiver<(AppEvents, Option<String>)>) {
+        let (sender, receiver) = mpsc::channel();
+        (Arc::new(sender), receiver)
+    }
+
+    #[test]
+    fn caps_how_many_jobs_run_at_once() {
+        let rt = Runtime::new().unwrap();
+        let queue = DownloadQueue::new(1, rt.handle().clone());
+        let (release_first, first_released) = mpsc::channel::<()>();
+        queue.enqueue("OpenLightsCore", "Open Lights Core", JobKind::Install, DownloadControl::default(), Progress::default(), app_events().0, Box::new(move |_, _| {
+            first_released.recv().unwrap();
+            JobStatus::Finished
+        }));
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Install, DownloadControl::default(), Progress::default(), app_events().0, Box::new(|_, _| JobStatus::Failed("offline".to_string())));
+
+        wait_until(&queue, |jobs| jobs[0].status == JobStatus::Active);
+        assert_eq!(queue.jobs()[1].status, JobStatus::Queued);
+
+        release_first.send(()).unwrap();
+        wait_until(&queue, |jobs| jobs.iter().all(|job| job.status.is_done()));
+        assert_eq!(queue.job_for("BeatMaker").unwrap().status, JobStatus::Failed("offline".to_string()));
+
+        // Retrying replaces the failed job
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Install, DownloadControl::default(), Progress::default(), app_events().0, Box::new(|_, _| JobStatus::Finished));
+        wait_until(&queue, |jobs| jobs.iter().all(|job| job.status == JobStatus::Finished));
+        assert_eq!(queue.jobs().len(), 2);
+        queue.clear_done();
+        assert!(queue.jobs().is_empty());
+    }
+
+    #[test]
+    fn queued_jobs_can_be_cancelled_before_they_start() {
+        let rt = Runtime::new().unwrap();
+        let queue = DownloadQueue::new(1, rt.handle().clone());
+        let (release_first, first_released) = mpsc::channel::<()>();
+        queue.enqueue("OpenLightsCore", "Open Lights Core", JobKind::Install, DownloadControl::default(), Progress::default(), app_events().0, Box::new(move |_, _| {
+            first_released.recv().unwrap();
+            JobStatus::Finished
+        }));
+        let control = DownloadControl::default();
+        let (events, received) = app_events();
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Update, control.clone(), Progress::default(), events, Box::new(|_, _| panic!("Cancelled jobs never run")));
+        control.cancel();
+
+        release_first.send(()).unwrap();
+        wait_until(&queue, |jobs| jobs.iter().all(|job| job.status.is_done()));
+        assert_eq!(queue.job_for("BeatMaker").unwrap().status, JobStatus::Cancelled);
+        // The app card leaves its downloading state too
+        assert_eq!(received.try_recv().unwrap(), (AppEvents::Cancelled, None));
+    }
+
+    #[test]
+    fn controls_are_reset_when_the_next_job_starts() {
+        let rt = Runtime::new().unwrap();
+        let queue = DownloadQueue::new(1, rt.handle().clone());
+        let control = DownloadControl::default();
+        let (release_first, first_released) = mpsc::channel::<()>();
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Install, control.clone(), Progress::default(), app_events().0, Box::new(move |control, _| {
+            first_released.recv().unwrap();
+            if control.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Finished }
+        }));
+        wait_until(&queue, |jobs| jobs[0].status == JobStatus::Active);
+        control.cancel();
+
+        // Queueing a retry doesn't undo the cancel the running job hasn't seen yet
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Version, control.clone(), Progress::default(), app_events().0, Box::new(|control, _| {
+            if control.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Finished }
+        }));
+        release_first.send(()).unwrap();
+        wait_until(&queue, |jobs| jobs.iter().all(|job| job.status.is_done()));
+        let statuses: Vec<JobStatus> = queue.jobs().into_iter().map(|job| job.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Cancelled, JobStatus::Finished]);
+    }
+
+    #[test]
+    fn panicking_jobs_free_their_place() {
+        let rt = Runtime::new().unwrap();
+        let queue = DownloadQueue::new(1, rt.handle().clone());
+        let (events, received) = app_events();
+        queue.enqueue("BeatMaker", "Beat Maker", JobKind::Install, DownloadControl::default(), Progress::default(), events, Box::new(|_, _| panic!("Disk unplugged")));
+        queue.enqueue("OpenLightsCore", "Open Lights Core", JobKind::Install, DownloadControl::default(), Progress::default(), app_events().0, Box::new(|_, _| JobStatus::Finished));
+
+        wait_until(&queue, |jobs| jobs.iter().all(|job| job.status.is_done()));
+        assert!(matches!(queue.job_for("BeatMaker").unwrap().status, JobStatus::Failed(_)));
+        assert_eq!(queue.job_for("OpenLightsCore").unwrap().status, JobStatus::Finished);
+        assert_eq!(received.try_recv().unwrap().0, AppEvents::Failed);
+    }
+}
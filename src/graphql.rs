@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use reqwest::header;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::gui::{AssetData, GithubData, ReleaseData};
+use crate::io::{check_status, GithubError};
+use crate::rate_limit::RateLimit;
+use crate::tasks::block_on;
+
+pub const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+// Matches the REST API's default page size so both backends see the same releases
+const RELEASES_PER_REPO: u32 = 30;
+const ASSETS_PER_RELEASE: u32 = 100;
+
+#[derive(Deserialize, Debug)]
+struct GraphqlResponse {
+    data: Option<HashMap<String, Option<GraphqlRepository>>>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlRepository {
+    name_with_owner: String,
+    description: Option<String>,
+    is_archived: bool,
+    releases: Nodes<GraphqlRelease>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlRelease {
+    tag_name: String,
+    is_prerelease: bool,
+    is_draft: bool,
+    database_id: i32,
+    release_assets: Nodes<GraphqlAsset>,
+    name: Option<String>,
+    description: Option<String>,
+    published_at: Option<DateTime<Utc>>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlAsset {
+    size: i32,
+    download_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+// Fetches the repository data and recent releases of every repo in a single request
+// Github only serves GraphQL to authenticated users, so a token is required
+pub fn fetch_repositories(endpoint: &str, token: &str, rate_limit: &mut RateLimit, repos: &[String]) -> Result<HashMap<String, (GithubData, Vec<ReleaseData>)>, GithubError> {
+    if repos.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let query = build_query(repos);
+    let response = block_on(post_query(endpoint, token, rate_limit, &query))?;
+    let parsed: GraphqlResponse = serde_json::from_str(&response).map_err(|e| GithubError::InvalidResponse(e.to_string()))?;
+
+    // Errors usually come from a repo that was renamed or deleted; the others are still usable
+    let data = parsed.data.ok_or_else(|| {
+        let messages: Vec<&str> = parsed.errors.iter().map(|error| error.message.as_str()).collect();
+        GithubError::InvalidResponse(messages.join("; "))
+    })?;
+
+    // Keyed by the repo as it was asked for; nameWithOwner can differ in case or after a rename
+    Ok(data.into_iter()
+        .filter_map(|(alias, repository)| {
+            let index: usize = alias.strip_prefix('r')?.parse().ok()?;
+            Some((repos.get(index)?.clone(), into_release_data(repository?)))
+        })
+        .collect())
+}
+
+fn build_query(repos: &[String]) -> String {
+    let mut query = String::from("query {");
+    for (index, repo) in repos.iter().enumerate() {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        // JSON string literals are valid GraphQL string literals
+        query.push_str(&format!(
+            " r{}: repository(owner: {}, name: {}) {{ nameWithOwner description isArchived releases(first: {}, orderBy: {{field: CREATED_AT, direction: DESC}}) {{ nodes {{ tagName isPrerelease isDraft databaseId name description publishedAt url releaseAssets(first: {}) {{ nodes {{ size downloadUrl }} }} }} }} }}",
+            index,
+            json!(owner),
+            json!(name),
+            RELEASES_PER_REPO,
+            ASSETS_PER_RELEASE,
+        ));
+    }
+    query.push_str(" }");
+    query
+}
+
+// GraphQL has its own hourly limit, so only a Retry-After carries over to the REST one
+async fn post_query(endpoint: &str, token: &str, rate_limit: &mut RateLimit, query: &str) -> Result<String, GithubError> {
+    if rate_limit.is_limited() {
+        return Err(GithubError::RateLimited(rate_limit.resumes_at()));
+    }
+    let mut graphql_rate_limit = RateLimit { retry_after_until: rate_limit.retry_after_until, ..RateLimit::default() };
+    let result = send_query(endpoint, token, &mut graphql_rate_limit, query).await;
+    rate_limit.retry_after_until = graphql_rate_limit.retry_after_until;
+    result
+}
+
+async fn send_query(endpoint: &str, token: &str, rate_limit: &mut RateLimit, query: &str) -> Result<String, GithubError> {
+    let client = reqwest::Client::new();
+    let response = client.post(endpoint)
+        .header(header::USER_AGENT, "Open-Lights-Manager")
+        .bearer_auth(token.trim())
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(json!({ "query": query }).to_string())
+        .send()
+        .await
+        .map_err(|e| GithubError::Network(e.to_string()))?;
+    check_status(&response, token, rate_limit)?;
+    response.text().await.map_err(|e| GithubError::Network(e.to_string()))
+}
+
+fn into_release_data(repository: GraphqlRepository) -> (GithubData, Vec<ReleaseData>) {
+    let github_data = GithubData {
+        description: repository.description.unwrap_or_default(),
+        archived: repository.is_archived,
+        releases_url: format!("https://api.github.com/repos/{}/releases{{/id}}", repository.name_with_owner),
+    };
+    let releases = repository.releases.nodes
+        .into_iter()
+        .map(|release| ReleaseData {
+            tag_name: release.tag_name,
+            prerelease: release.is_prerelease,
+            draft: release.is_draft,
+            id: release.database_id,
+            assets: release.release_assets.nodes
+                .into_iter()
+                .map(|asset| AssetData {
+                    size: asset.size,
+                    browser_download_url: asset.download_url,
+                    digest: None,
+                })
+                .collect(),
+            name: release.name,
+            body: release.description,
+            published_at: release.published_at,
+            html_url: release.url,
+        })
+        .collect();
+    (github_data, releases)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    // Answers a single request with the given JSON body and hands back what was received
+    fn stand_in_endpoint(body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            let mut request = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            request.push_str(&String::from_utf8(request_body).unwrap());
+
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-RateLimit-Resource: graphql\r\nX-RateLimit-Remaining: 4999\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+            request
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn maps_batched_response_into_release_data() {
+        let body = r#"{"data": {
+            "r0": {"nameWithOwner": "Open-Lights/BeatMakerStudio", "description": "Make beats", "isArchived": false, "releases": {"nodes": [
+                {"tagName": "v2.0.0-pre.1", "isPrerelease": true, "isDraft": false, "databaseId": 3, "releaseAssets": {"nodes": []}},
+                {"tagName": "v1.1.0", "isPrerelease": false, "isDraft": true, "databaseId": 2, "releaseAssets": {"nodes": []}},
+                {"tagName": "v1.0.0", "isPrerelease": false, "isDraft": false, "databaseId": 1, "name": "First light", "description": "- Initial release", "publishedAt": "2024-05-01T12:00:00Z", "url": "https://github.com/Open-Lights/BeatMaker/releases/tag/v1.0.0", "releaseAssets": {"nodes": [
+                    {"size": 42, "downloadUrl": "https://github.com/Open-Lights/BeatMaker/releases/download/v1.0.0/OpenLightsBeatMaker.jar"}
+                ]}}
+            ]}},
+            "r1": null
+        }, "errors": [{"message": "Could not resolve to a Repository with the name 'Open-Lights/Missing'."}]}"#;
+        let (endpoint, handle) = stand_in_endpoint(body);
+        let repos = vec!["Open-Lights/BeatMaker".to_string(), "Open-Lights/Missing".to_string()];
+
+        let mut rate_limit = RateLimit::default();
+        let result = fetch_repositories(&endpoint, "token", &mut rate_limit, &repos).unwrap();
+        let request = handle.join().unwrap();
+        // The GraphQL limit isn't the REST one
+        assert_eq!(rate_limit, RateLimit::default());
+
+        assert!(request.to_ascii_lowercase().contains("authorization: bearer token"));
+        assert!(request.contains(r#"r0: repository(owner: \"Open-Lights\", name: \"BeatMaker\")"#));
+        assert!(request.contains(r#"r1: repository(owner: \"Open-Lights\", name: \"Missing\")"#));
+
+        // Found under the name it was asked for, even after the repo was renamed
+        assert_eq!(result.len(), 1);
+        let (github_data, releases) = &result["Open-Lights/BeatMaker"];
+        assert_eq!(github_data.description, "Make beats");
+        assert!(!github_data.archived);
+        assert_eq!(github_data.releases_url, "https://api.github.com/repos/Open-Lights/BeatMakerStudio/releases{/id}");
+        let tags: Vec<&str> = releases.iter().map(|release| release.tag_name.as_str()).collect();
+        assert_eq!(tags, ["v2.0.0-pre.1", "v1.1.0", "v1.0.0"]);
+        assert!(releases[1].draft);
+        assert_eq!(releases[2].id, 1);
+        assert_eq!(releases[2].assets[0].size, 42);
+        assert_eq!(releases[2].body.as_deref(), Some("- Initial release"));
+        assert_eq!(releases[2].published_at.unwrap().to_rfc3339(), "2024-05-01T12:00:00+00:00");
+        assert_eq!(releases[0].name, None);
+    }
+}
@@ -1,22 +1,36 @@
-use std::cmp::PartialEq;
+use std::cmp::{PartialEq, Reverse};
 use std::collections::VecDeque;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::atomic::{AtomicI8, AtomicU32, Ordering};
+use std::sync::atomic::AtomicU32;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
-use std::{fs, i32};
+use std::fs;
 
+use chrono::{DateTime, Local, Utc};
 use egui::TextStyle::Body;
-use egui::{pos2, CentralPanel, Color32, Context, FontFamily, FontId, Frame, Image, Pos2, ProgressBar, Rect, RichText, Rounding, Stroke, TextStyle, Ui, Vec2};
+use egui::{pos2, CentralPanel, Color32, Context, FontFamily, FontId, Frame, Image, Pos2, ProgressBar, Rect, RichText, Rounding, Sense, Stroke, TextStyle, Ui, Vec2};
 use egui_file::FileDialog;
 use serde::{Deserialize, Serialize};
 
-use crate::io::{check_for_all_updates, download_application, gather_app_data, launch_application, save_app_data_offline, should_check_github, update, update_app_data, AppEvents, InstallationData};
-use crate::notifications::{app_installation_failure, app_installation_success, java_failure_corrupted, java_failure_invalid, java_failure_issue, java_success, manager_installation_success, rate_limit_notification};
+use crate::archive::format_size;
+use crate::catalog::load_catalog;
+use crate::checksum::ChecksumStatus;
+use crate::download::DownloadControl;
+use crate::download_queue::{DownloadJob, DownloadQueue, JobKind, JobStatus};
+use crate::io::{activate_version, apply_latest_release, check_for_all_updates, download_application, gather_app_data, get_all_versions, install_version, launch_application, parse_semver, releases_between, roll_back, save_app_data_offline, set_pinned, set_update_policy, should_check_github, update, update_app_data, update_check_cost, AppEvents, GithubError, InstallationData, UpdateCheck, UpdateTarget};
+use crate::notifications::{app_installation_cancelled, app_installation_failure, app_installation_failure_reason, app_installation_success, app_rollback_failure, app_rolled_back, app_uninstall_failure, app_update_available, app_update_success, app_version_activated, app_version_installed, app_version_switch_failure, bad_token_notification, java_failure_corrupted, java_failure_invalid, java_failure_issue, java_success, manager_installation_success, rate_limit_notification};
+use crate::markdown::render_markdown;
+use crate::progress::{Phase, Progress};
+use crate::scheduler::{check_interval, UpdateScheduler, MIN_CHECK_INTERVAL_MINUTES};
 use crate::settings::{load_settings, Settings};
+use crate::signature::SignaturePolicy;
+use crate::source::SourceConfig;
+use crate::staging::AppDirs;
+use crate::tasks::{Task, TaskState, Tasks};
+use crate::update_policy::{UpdateMode, UpdatePolicy, UpdateRange};
 
 pub struct OpenLightsManager {
     current_screen: Screen,
@@ -25,14 +39,53 @@ pub struct OpenLightsManager {
     settings: Settings,
     theme: Theme,
     file_explorer: FileExplorer,
+    download_queue: DownloadQueue,
+    tasks: Tasks,
+    loading_apps: Option<Task<LoadedApps>>,
+    update_check: Option<Task<(UpdateCheck, Settings)>>,
+    update_check_in_background: bool, // Scheduled checks only speak up when they find something
+    scheduler: UpdateScheduler,
+    releases_view: Option<ReleasesView>,
 }
 
-#[derive(PartialEq, Default)]
+// The apps, any Github error to show, and the settings as the checks left them
+type LoadedApps = (Vec<App>, Option<Notification>, Settings);
+
+// Every release of the app, and the settings as the request left them
+type ReleaseList = (Result<Vec<ReleaseData>, GithubError>, Settings);
+
+// The app whose releases are being shown, on the versions or details screen
+struct ReleasesView {
+    app_name: String,
+    releases: Option<Result<Vec<ReleaseData>, String>>, // None while loading
+    task: Option<Task<ReleaseList>>,
+    back_to: Screen, // The tab it was opened from
+}
+
+// Something an app card asks the manager to do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CardAction {
+    ShowVersions,
+    ShowDetails,
+}
+
+// What was clicked on the versions screen, acted on once drawing is done
+enum VersionAction {
+    Use(String),
+    Remove(String),
+    Install(ReleaseData),
+    Pin(bool),
+}
+
+#[derive(PartialEq, Default, Clone, Copy)]
 enum Screen {
     #[default]
     Installed,
     Settings,
     Browse,
+    Downloads,
+    Versions, // Opened from an app's menu rather than the taskbar
+    Details, // Opened by clicking an app's name
 }
 
 #[inline]
@@ -72,6 +125,8 @@ fn configure_text_styles(ctx: &Context) {
 impl eframe::App for OpenLightsManager {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.poll_tasks(ctx);
+        self.run_scheduled_checks(ctx);
         show_notification(ctx, &mut self.notifications, &self.theme);
 
         CentralPanel::default().show(ctx, |ui| {
@@ -87,7 +142,10 @@ impl eframe::App for OpenLightsManager {
             match self.current_screen {
                 Screen::Installed => self.render_installation(ui),
                 Screen::Browse => self.render_browse(ui),
+                Screen::Downloads => self.render_downloads(ui),
                 Screen::Settings => self.render_settings(ui),
+                Screen::Versions => self.render_versions(ui),
+                Screen::Details => self.render_details(ui),
             }
         });
     }
@@ -107,29 +165,184 @@ impl OpenLightsManager {
     pub fn new(ctx: &Context) -> Self {
         configure_text_styles(ctx);
 
-        let mut settings = load_settings();
-        let mut notifications = VecDeque::new();
-        let apps_pre = gather_app_data(settings.unstable_releases, &mut settings);
-        if let Some(notification) = apps_pre.1 {
-            notify(ctx, notification, &mut notifications);
-        };
-        let apps = apps_pre.0;
+        let settings = load_settings();
+        let catalog = load_catalog();
+        let tasks = Tasks::new(Some(ctx.clone()));
+        // Checking every app can take a while, so the window opens first
+        let mut checked_settings = settings.clone();
+        let loading_apps = tasks.spawn_blocking(move || {
+            let (apps, notification) = gather_app_data(&catalog, checked_settings.unstable_releases, &mut checked_settings);
+            (apps, notification, checked_settings)
+        });
         let theme = Theme::get_theme(&settings);
         let file_explorer = FileExplorer {
             opened_file: None,
             open_file_dialog: None,
         };
+        let download_queue = DownloadQueue::new(settings.max_concurrent_downloads, tasks.handle());
 
         OpenLightsManager {
             current_screen: Screen::default(),
-            notifications,
-            apps,
+            notifications: VecDeque::new(),
+            apps: Vec::new(),
             settings,
             theme,
             file_explorer,
+            download_queue,
+            tasks,
+            loading_apps: Some(loading_apps),
+            update_check: None,
+            update_check_in_background: false,
+            scheduler: UpdateScheduler::default(),
+            releases_view: None,
+        }
+    }
+
+    // Picks up the results of background work started on earlier frames
+    fn poll_tasks(&mut self, ctx: &Context) {
+        if let Some(task) = &self.loading_apps {
+            match task.poll() {
+                TaskState::Running => {}
+                TaskState::Finished((apps, notification, checked_settings)) => {
+                    self.apps = apps;
+                    self.settings.merge_github_state(&checked_settings);
+                    if let Some(notification) = notification {
+                        notify(ctx, notification, &mut self.notifications);
+                    }
+                    self.loading_apps = None;
+                    self.install_automatic_updates();
+                }
+                TaskState::Failed => {
+                    println!("Failed to load the apps");
+                    self.loading_apps = None;
+                }
+            }
+        }
+
+        if let Some(task) = &self.update_check {
+            match task.poll() {
+                TaskState::Running => {}
+                TaskState::Finished((check, checked_settings)) => {
+                    for (name, release_data) in check.releases {
+                        if let Some(app) = self.apps.iter_mut().find(|app| app.name == name) {
+                            let had_update = app.has_update;
+                            apply_latest_release(app, release_data);
+                            if app.has_update && !had_update && app.update_policy.mode == UpdateMode::Notify {
+                                let notification = app_update_available(&app.display_name, &app.release_data.tag_name);
+                                notify(ctx, notification, &mut self.notifications);
+                            }
+                        }
+                    }
+                    self.settings.merge_github_state(&checked_settings);
+                    match check.error {
+                        Some(error) if self.update_check_in_background => println!("Scheduled update check failed: {:?}", error),
+                        Some(error) => notify(ctx, error.notification(), &mut self.notifications),
+                        None => {}
+                    }
+                    self.update_check = None;
+                    self.install_automatic_updates();
+                }
+                TaskState::Failed => {
+                    println!("Failed to check for updates");
+                    self.update_check = None;
+                }
+            }
+        }
+
+        if let Some(view) = &mut self.releases_view {
+            if let Some(task) = &view.task {
+                match task.poll() {
+                    TaskState::Running => {}
+                    TaskState::Finished((releases, checked_settings)) => {
+                        view.releases = Some(releases.map_err(|error| error.to_string()));
+                        view.task = None;
+                        self.settings.merge_github_state(&checked_settings);
+                    }
+                    TaskState::Failed => {
+                        view.releases = Some(Err("Failed to list the releases".to_string()));
+                        view.task = None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn install_automatic_updates(&mut self) {
+        for app in self.apps.iter_mut() {
+            if app.installs_update_automatically(&self.settings) {
+                app.install_update_automatically(&self.settings, &self.download_queue);
+            }
+        }
+    }
+
+    // Starts a check in the background once the interval has passed
+    fn run_scheduled_checks(&mut self, ctx: &Context) {
+        if self.update_check.is_some() || self.loading_apps.is_some() || !self.settings.scheduled_update_checks {
+            return;
+        }
+        let cost = update_check_cost(&self.settings, &self.apps);
+        let now = Utc::now();
+        if !self.scheduler.is_due(&self.settings, cost, now) {
+            // Nothing else wakes the UI up while the window sits idle
+            if let Ok(wait) = self.scheduler.time_until_due(&self.settings, cost, now).to_std() {
+                ctx.request_repaint_after(wait);
+            }
+            return;
+        }
+        if should_check_github(&self.settings, cost) {
+            println!("Running a scheduled update check");
+            self.scheduler.started(now);
+            self.check_for_updates(true);
+        } else {
+            self.scheduler.rate_limited(&self.settings, now);
+        }
+    }
+
+    fn show_releases(&mut self, app_name: &str, screen: Screen) {
+        let Some(app) = self.apps.iter().find(|app| app.name == app_name) else {
+            return;
+        };
+        let target = UpdateTarget::of(app);
+        let mut checked_settings = self.settings.clone();
+        let task = self.tasks.spawn_blocking(move || {
+            let releases = get_all_versions(&target, &mut checked_settings);
+            (releases, checked_settings)
+        });
+        let back_to = match &self.releases_view {
+            Some(view) if matches!(self.current_screen, Screen::Versions | Screen::Details) => view.back_to,
+            _ => self.current_screen,
+        };
+        self.releases_view = Some(ReleasesView {
+            app_name: app_name.to_string(),
+            releases: None,
+            task: Some(task),
+            back_to,
+        });
+        self.current_screen = screen;
+    }
+
+    fn close_releases(&mut self) {
+        self.current_screen = self.releases_view.take().map_or(Screen::Installed, |view| view.back_to);
+    }
+
+    fn handle_card_action(&mut self, card_action: Option<(String, CardAction)>) {
+        match card_action {
+            Some((app_name, CardAction::ShowVersions)) => self.show_releases(&app_name, Screen::Versions),
+            Some((app_name, CardAction::ShowDetails)) => self.show_releases(&app_name, Screen::Details),
+            None => {}
         }
     }
 
+    fn check_for_updates(&mut self, in_background: bool) {
+        self.update_check_in_background = in_background;
+        let targets: Vec<UpdateTarget> = self.apps.iter().map(UpdateTarget::of).collect();
+        let mut checked_settings = self.settings.clone();
+        self.update_check = Some(self.tasks.spawn_blocking(move || {
+            let check = check_for_all_updates(&targets, checked_settings.unstable_releases, &mut checked_settings);
+            (check, checked_settings)
+        }));
+    }
+
     pub fn render_background(&mut self, ui: &mut Ui) {
         let rect = Rect::from_two_pos(Pos2 {x: -10., y: 0.}, Pos2 {x: 610., y: 610.});
         Image::new(egui::include_image!("../assets/background.png"))
@@ -142,39 +355,55 @@ impl OpenLightsManager {
         let rect = Rect::from_two_pos(Pos2 {x: 20., y: 80.}, Pos2 {x: 580., y: 130.});
         ui.painter().rect(rect, Rounding::same(16.), self.theme.panel, Stroke::NONE);
 
-        let rect1 = Rect::from_two_pos(pos2(80., 80.), pos2(150., 130.));
+        let rect1 = Rect::from_two_pos(pos2(50., 80.), pos2(120., 130.));
         if ui.put(rect1,
                   egui::Label::new(RichText::new("Installed").color(self.theme.text))
         ).clicked() {
             self.current_screen = Screen::Installed;
         };
 
-        let rect2 = Rect::from_two_pos(pos2(250., 80.), pos2(350., 130.));
+        let rect2 = Rect::from_two_pos(pos2(165., 80.), pos2(255., 130.));
         if ui.put(rect2,
                   egui::Label::new(RichText::new("Browse").color(self.theme.text))
         ).clicked() {
             self.current_screen = Screen::Browse;
         };
 
-        let rect3 = Rect::from_two_pos(pos2(450., 80.), pos2(520., 130.));
+        let rect3 = Rect::from_two_pos(pos2(300., 80.), pos2(395., 130.));
         if ui.put(rect3,
+                  egui::Label::new(RichText::new("Downloads").color(self.theme.text))
+        ).clicked() {
+            self.current_screen = Screen::Downloads;
+        };
+
+        let rect4 = Rect::from_two_pos(pos2(450., 80.), pos2(520., 130.));
+        if ui.put(rect4,
                   egui::Label::new(RichText::new("Settings").color(self.theme.text))
         ).clicked() {
             self.current_screen = Screen::Settings;
         };
 
-        match self.current_screen {
-            Screen::Installed => {
-                let rect4 = Rect::from_two_pos(pos2(80., 120.), pos2(150., 125.));
-                ui.painter().rect(rect4, Rounding::same(16.), self.theme.text, Stroke::NONE);
+        // Screens opened from an app keep the tab they came from underlined
+        let tab = match (&self.releases_view, self.current_screen) {
+            (Some(view), Screen::Versions | Screen::Details) => view.back_to,
+            (_, screen) => screen,
+        };
+        match tab {
+            Screen::Installed | Screen::Versions | Screen::Details => {
+                let rect5 = Rect::from_two_pos(pos2(50., 120.), pos2(120., 125.));
+                ui.painter().rect(rect5, Rounding::same(16.), self.theme.text, Stroke::NONE);
             }
             Screen::Browse => {
-                let rect4 = Rect::from_two_pos(pos2(265., 120.), pos2(335., 125.));
-                ui.painter().rect(rect4, Rounding::same(16.), self.theme.text, Stroke::NONE);
+                let rect5 = Rect::from_two_pos(pos2(175., 120.), pos2(245., 125.));
+                ui.painter().rect(rect5, Rounding::same(16.), self.theme.text, Stroke::NONE);
+            }
+            Screen::Downloads => {
+                let rect5 = Rect::from_two_pos(pos2(305., 120.), pos2(390., 125.));
+                ui.painter().rect(rect5, Rounding::same(16.), self.theme.text, Stroke::NONE);
             }
             Screen::Settings => {
-                let rect4 = Rect::from_two_pos(pos2(450., 120.), pos2(520., 125.));
-                ui.painter().rect(rect4, Rounding::same(16.), self.theme.text, Stroke::NONE);
+                let rect5 = Rect::from_two_pos(pos2(450., 120.), pos2(520., 125.));
+                ui.painter().rect(rect5, Rounding::same(16.), self.theme.text, Stroke::NONE);
             }
         }
     }
@@ -191,6 +420,24 @@ impl OpenLightsManager {
         self.render_app_panel(ui, false);
     }
 
+    pub fn render_downloads(&mut self, ui: &mut Ui) {
+        let rect = Self::tab_area();
+        ui.painter().rect(rect, Rounding::same(16.), self.theme.panel, Stroke::NONE);
+        self.render_downloads_panel(ui);
+    }
+
+    pub fn render_versions(&mut self, ui: &mut Ui) {
+        let rect = Self::tab_area();
+        ui.painter().rect(rect, Rounding::same(16.), self.theme.panel, Stroke::NONE);
+        self.render_versions_panel(ui);
+    }
+
+    pub fn render_details(&mut self, ui: &mut Ui) {
+        let rect = Self::tab_area();
+        ui.painter().rect(rect, Rounding::same(16.), self.theme.panel, Stroke::NONE);
+        self.render_details_panel(ui);
+    }
+
     pub fn render_settings(&mut self, ui: &mut Ui) {
         let rect = Self::tab_area();
         ui.painter().rect(rect, Rounding::same(16.), self.theme.panel, Stroke::NONE);
@@ -199,20 +446,271 @@ impl OpenLightsManager {
 
     fn render_app_panel(&mut self, ui: &mut Ui, install_only: bool) {
         let rect = Self::scroll_area();
+        let mut card_action = None;
 
         ui.allocate_ui_at_rect(rect, |ui| {
             egui::ScrollArea::vertical()
                 .max_height(420.)
                 .max_width(550.)
                 .show(ui, |ui| {
+                    if self.loading_apps.is_some() {
+                        ui.add_sized([520., 40.], egui::Label::new(RichText::new("Loading apps...").color(self.theme.text)));
+                    }
                     for app in self.apps.iter_mut(){
                         if (install_only && app.installed) || (!install_only && !app.installed) {
-                            app.render(ui, &self.theme, &mut self.notifications, &mut self.settings);
+                            if let Some(action) = app.render(ui, &self.theme, &mut self.notifications, &mut self.settings, &self.download_queue) {
+                                card_action = Some((app.name.clone(), action));
+                            }
                             ui.add_space(10.);
                         }
                     }
                 });
         });
+
+        self.handle_card_action(card_action);
+    }
+
+    fn render_versions_panel(&mut self, ui: &mut Ui) {
+        let rect = Self::scroll_area();
+        let Some(view) = &self.releases_view else {
+            self.current_screen = Screen::Installed;
+            return;
+        };
+        let Some(app) = self.apps.iter_mut().find(|app| app.name == view.app_name) else {
+            self.close_releases();
+            return;
+        };
+
+        let dirs = AppDirs::new(&app.name);
+        let mut installed_versions = dirs.installed_versions();
+        installed_versions.sort_by_key(|version| Reverse(parse_semver(version)));
+        let mut back = false;
+        let mut action = None;
+        let mut card_action = None;
+        ui.allocate_ui_at_rect(rect, |ui| {
+            ui.horizontal(|ui| {
+                if ui.add_sized([80., 30.], egui::Button::new(RichText::new("Back").color(self.theme.text))).clicked() {
+                    back = true;
+                }
+                ui.add_sized([300., 30.], egui::Label::new(RichText::new(format!("{} versions", app.display_name)).color(self.theme.text)));
+                let mut pinned = app.pinned;
+                if ui.checkbox(&mut pinned, RichText::new("Pinned").color(self.theme.text)).on_hover_text("Pinned apps stay on their version; update checks skip them").changed() {
+                    action = Some(VersionAction::Pin(pinned));
+                }
+            });
+            egui::ScrollArea::vertical()
+                .max_height(380.)
+                .max_width(550.)
+                .show(ui, |ui| {
+                    // The card keeps handling the app's events and shows install progress
+                    card_action = app.render(ui, &self.theme, &mut self.notifications, &mut self.settings, &self.download_queue).map(|action| (app.name.clone(), action));
+                    let installing = app.event == AppEvents::Downloading || app.event == AppEvents::Extracting;
+                    // Versions are only swapped or removed while the app is closed, like updates
+                    let busy = installing || app.event == AppEvents::Running;
+                    ui.add_space(10.);
+
+                    if app.installed {
+                        version_row(ui, &self.theme, &app.version, "Active", |_| {});
+                    }
+                    for version in &installed_versions {
+                        version_row(ui, &self.theme, version, "Installed", |ui| {
+                            ui.add_enabled_ui(!busy, |ui| {
+                                if ui.button(RichText::new("Use").color(self.theme.text)).clicked() {
+                                    action = Some(VersionAction::Use(version.clone()));
+                                }
+                                if ui.button(RichText::new("Remove").color(self.theme.text)).clicked() {
+                                    action = Some(VersionAction::Remove(version.clone()));
+                                }
+                            });
+                        });
+                    }
+
+                    match &view.releases {
+                        None => {
+                            ui.add_sized([520., 40.], egui::Label::new(RichText::new("Loading releases...").color(self.theme.text)));
+                        }
+                        Some(Err(error)) => {
+                            ui.add_sized([520., 40.], egui::Label::new(RichText::new(error).color(self.theme.text)));
+                        }
+                        Some(Ok(releases)) => {
+                            for release in releases {
+                                if (app.installed && release.tag_name == app.version) || dirs.has_version(&release.tag_name) {
+                                    continue;
+                                }
+                                let status = if release.prerelease { "Prerelease" } else { "" };
+                                version_row(ui, &self.theme, &release.tag_name, status, |ui| {
+                                    if ui.add_enabled(!installing, egui::Button::new(RichText::new("Install").color(self.theme.text))).clicked() {
+                                        action = Some(VersionAction::Install(release.clone()));
+                                    }
+                                });
+                            }
+                        }
+                    }
+                });
+        });
+
+        let notification = match action {
+            Some(VersionAction::Use(version)) => Some(match activate_version(app, &version) {
+                Ok(()) => app_version_activated(&app.display_name, &version),
+                Err(e) => app_version_switch_failure(&app.display_name, &e.to_string()),
+            }),
+            Some(VersionAction::Remove(version)) => {
+                if let Err(e) = dirs.remove_version(&version) {
+                    println!("Failed to remove {} {}: {}", app.name, version, e);
+                }
+                if app.previous_version.as_deref() == Some(version.as_str()) {
+                    app.previous_version = None;
+                    save_app_data_offline(app);
+                }
+                None
+            }
+            Some(VersionAction::Install(release_data)) => {
+                app.event = AppEvents::Downloading;
+                install_version(app, release_data, &self.settings, &app.progress, &app.thread_communication.event_sender, &self.download_queue);
+                None
+            }
+            Some(VersionAction::Pin(pinned)) => {
+                set_pinned(app, pinned);
+                None
+            }
+            None => None,
+        };
+        if let Some(notification) = notification {
+            notify(ui.ctx(), notification, &mut self.notifications);
+        }
+        if back {
+            self.close_releases();
+        }
+        self.handle_card_action(card_action);
+    }
+
+    fn render_details_panel(&mut self, ui: &mut Ui) {
+        let rect = Self::scroll_area();
+        let Some(view) = &self.releases_view else {
+            self.current_screen = Screen::Installed;
+            return;
+        };
+        let Some(app) = self.apps.iter_mut().find(|app| app.name == view.app_name) else {
+            self.close_releases();
+            return;
+        };
+
+        let mut back = false;
+        let mut card_action = None;
+        ui.allocate_ui_at_rect(rect, |ui| {
+            ui.horizontal(|ui| {
+                if ui.add_sized([80., 30.], egui::Button::new(RichText::new("Back").color(self.theme.text))).clicked() {
+                    back = true;
+                }
+                ui.add_sized([300., 30.], egui::Label::new(RichText::new(&app.display_name).color(self.theme.text)));
+                if app.source.is_github() {
+                    ui.hyperlink_to(RichText::new("Repository").color(self.theme.text), format!("https://github.com/{}", app.github_repo));
+                }
+            });
+            egui::ScrollArea::vertical()
+                .max_height(380.)
+                .max_width(550.)
+                .show(ui, |ui| {
+                    // The card keeps handling the app's events and shows install progress
+                    card_action = app.render(ui, &self.theme, &mut self.notifications, &mut self.settings, &self.download_queue).map(|action| (app.name.clone(), action));
+                    ui.add_space(10.);
+
+                    let releases = match &view.releases {
+                        Some(Ok(releases)) => Some(releases.as_slice()),
+                        _ => None,
+                    };
+                    let (title, notes) = notes_to_show(app, releases, self.settings.unstable_releases);
+                    ui.label(RichText::new(title).color(self.theme.text).size(18.).strong());
+                    if view.releases.is_none() && app.has_update {
+                        ui.label(RichText::new("Loading the notes of skipped versions...").color(self.theme.text).text_style(notification_font()));
+                    }
+                    if notes.is_empty() {
+                        ui.label(RichText::new("No release notes were found.").color(self.theme.text));
+                    }
+                    for release in &notes {
+                        ui.separator();
+                        release_notes(ui, &self.theme, release);
+                    }
+                });
+        });
+
+        if back {
+            self.close_releases();
+        }
+        self.handle_card_action(card_action);
+    }
+
+    fn render_downloads_panel(&mut self, ui: &mut Ui) {
+        let rect = Self::scroll_area();
+        let jobs = self.download_queue.jobs();
+        if jobs.iter().any(|job| !job.status.is_done()) {
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+        }
+
+        let mut retry = None;
+        ui.allocate_ui_at_rect(rect, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_sized([430., 30.], egui::Label::new(RichText::new(format!("{} downloads", jobs.len())).color(self.theme.text)));
+                if ui.add_sized([80., 30.], egui::Button::new(RichText::new("Clear").color(self.theme.text))).on_hover_text("Removes finished, failed and cancelled downloads").clicked() {
+                    self.download_queue.clear_done();
+                }
+            });
+            egui::ScrollArea::vertical()
+                .max_height(380.)
+                .max_width(550.)
+                .show(ui, |ui| {
+                    if jobs.is_empty() {
+                        ui.add_sized([520., 40.], egui::Label::new(RichText::new("Nothing is downloading").color(self.theme.text)));
+                    }
+                    for job in jobs.iter().rev() {
+                        ui.horizontal(|ui| {
+                            let kind = match job.kind {
+                                JobKind::Install => "Install",
+                                JobKind::Update => "Update",
+                                JobKind::Version => "Install version",
+                            };
+                            ui.vertical(|ui| {
+                                ui.add_sized([420., 20.], egui::Label::new(RichText::new(format!("{} - {}", job.display_name, kind)).color(self.theme.text).strong()));
+                                ui.add_sized([420., 20.], egui::Label::new(RichText::new(describe_job(job)).color(self.theme.text).text_style(notification_font())));
+                                if job.status == JobStatus::Active {
+                                    let bar = match job.progress.fraction() {
+                                        Some(fraction) => ProgressBar::new(fraction),
+                                        None => ProgressBar::new(0.).animate(true),
+                                    };
+                                    ui.add_sized([420., 6.], bar);
+                                }
+                            });
+                            match &job.status {
+                                JobStatus::Queued | JobStatus::Active => {
+                                    if ui.add_enabled(!job.control.is_cancelled(), egui::Button::new(RichText::new("Cancel").color(self.theme.text))).clicked() {
+                                        job.control.cancel();
+                                    }
+                                }
+                                JobStatus::Failed(_) | JobStatus::Cancelled => {
+                                    // Versions are installed again from the versions screen
+                                    if job.kind != JobKind::Version && ui.button(RichText::new("Retry").color(self.theme.text)).clicked() {
+                                        retry = Some(job.clone());
+                                    }
+                                }
+                                JobStatus::Finished => {}
+                            }
+                        });
+                        ui.add_space(10.);
+                    }
+                });
+        });
+
+        if let Some(job) = retry {
+            if let Some(app) = self.apps.iter_mut().find(|app| app.name == job.app_name) {
+                if job.kind == JobKind::Update && app.installed && app.has_update {
+                    app.event = AppEvents::Downloading;
+                    update(app, &self.settings, &app.progress, &app.thread_communication.event_sender, &self.download_queue);
+                } else if job.kind == JobKind::Install && !app.installed {
+                    app.event = AppEvents::Downloading;
+                    download_application(app, &self.settings, &app.progress, &app.thread_communication.event_sender, &self.download_queue);
+                }
+            }
+        }
     }
 
     fn render_settings_panel(&mut self, ui: &mut Ui) {
@@ -311,14 +809,68 @@ impl OpenLightsManager {
                         }
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100., 50.], egui::Label::new(RichText::new("Batch Update Checks").color(self.theme.text)));
+                        if ui.add_sized([50., 50.], egui::Checkbox::without_text(&mut self.settings.batch_update_checks)).on_hover_text("Checks every app in one request. Requires a Github Token.").clicked() {
+                            self.settings.save_settings();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100., 50.], egui::Label::new(RichText::new("Release Page Limit").color(self.theme.text)));
+                        let response = ui.add_sized([50., 30.], egui::DragValue::new(&mut self.settings.release_page_limit).range(1..=20)).on_hover_text("How many pages of 30 releases to search for one on your channel. Each page is one request.");
+                        if response.drag_stopped() || response.lost_focus() {
+                            self.settings.save_settings();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100., 50.], egui::Label::new(RichText::new("Parallel Downloads").color(self.theme.text)));
+                        let response = ui.add_sized([50., 30.], egui::DragValue::new(&mut self.settings.max_concurrent_downloads).range(1..=5)).on_hover_text("How many apps may download at the same time. The rest wait in line.");
+                        if response.changed() {
+                            self.download_queue.set_max_concurrent(self.settings.max_concurrent_downloads);
+                        }
+                        if response.drag_stopped() || response.lost_focus() {
+                            self.settings.save_settings();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100., 50.], egui::Label::new(RichText::new("Background Checks").color(self.theme.text)));
+                        if ui.add_sized([50., 50.], egui::Checkbox::without_text(&mut self.settings.scheduled_update_checks)).on_hover_text("Checks for updates while the manager is open.").clicked() {
+                            self.settings.save_settings();
+                        }
+                        ui.add_enabled_ui(self.settings.scheduled_update_checks, |ui| {
+                            ui.add_sized([60., 50.], egui::Label::new(RichText::new("Every").color(self.theme.text)));
+                            let interval = check_interval(&self.settings, update_check_cost(&self.settings, &self.apps)).num_minutes();
+                            let hover = if interval > self.settings.update_check_interval_minutes as i64 {
+                                format!("Minutes between checks. Your apps need at least {} to stay within Github's rate limit.", interval)
+                            } else {
+                                "Minutes between checks.".to_string()
+                            };
+                            let response = ui.add_sized([50., 30.], egui::DragValue::new(&mut self.settings.update_check_interval_minutes).range(MIN_CHECK_INTERVAL_MINUTES..=1440).suffix(" min")).on_hover_text(hover);
+                            if response.drag_stopped() || response.lost_focus() {
+                                self.settings.save_settings();
+                            }
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100., 50.], egui::Label::new(RichText::new("Auto-Install Updates").color(self.theme.text)));
+                        if ui.add_sized([50., 50.], egui::Checkbox::without_text(&mut self.settings.auto_install_updates)).on_hover_text("Installs updates for apps set to install them automatically, once they aren't running.").clicked() {
+                            self.settings.save_settings();
+                        }
+                    });
+
                     ui.horizontal(|ui| {
                         ui.add_sized([100., 50.], egui::Label::new(RichText::new("Last Update Check: ").color(self.theme.text)));
                         ui.add_sized([100., 50.], egui::Label::new(RichText::new(&self.settings.last_github_check_formatted).color(self.theme.text)));
-                        if ui.add_sized([25., 25.], egui::Button::new(RichText::new("↻").color(self.theme.text))).clicked() {
-                            if should_check_github(&self.settings) {
-                                check_for_all_updates(&mut self.apps, self.settings.unstable_releases, &mut self.settings)
+                        let checking = self.update_check.is_some() || self.loading_apps.is_some();
+                        if ui.add_enabled_ui(!checking, |ui| ui.add_sized([25., 25.], egui::Button::new(RichText::new("↻").color(self.theme.text)))).inner.clicked() {
+                            if should_check_github(&self.settings, update_check_cost(&self.settings, &self.apps)) {
+                                self.check_for_updates(false);
                             } else {
-                                let notification = rate_limit_notification();
+                                let notification = rate_limit_notification(self.settings.rate_limit.resumes_at());
                                 notify(ui.ctx(), notification, &mut self.notifications);
                             }
                         }
@@ -461,6 +1013,8 @@ pub struct App {
     #[serde(skip)]
     pub event: AppEvents,
     pub(crate) name: String,
+    #[serde(skip)]
+    pub display_name: String,
     pub path: String, // This is the app_data path
     pub app_path: String, // This is the executable path
     pub version: String,
@@ -472,18 +1026,34 @@ pub struct App {
     pub update_download_url: Option<String>,
     pub(crate) launchable: bool,
     #[serde(skip)]
-    pub progress: Arc<AtomicI8>,
+    pub progress: Progress,
     #[serde(skip)]
     pub thread_communication: ThreadCommunication,
     #[serde(skip)]
     pub process: Arc<AtomicU32>,
     #[serde(skip)]
     pub installation_data: InstallationData,
+    #[serde(skip)]
+    pub source: SourceConfig,
+    #[serde(default)]
+    pub checksum: ChecksumStatus, // How the installed download was verified
+    #[serde(default)]
+    pub signature_policy: SignaturePolicy, // Ignored for the manager, which always requires a signature from a key pinned in the catalog
+    #[serde(skip)]
+    pub download_control: DownloadControl,
+    #[serde(default)]
+    pub previous_version: Option<String>, // Kept on disk by the last update so it can be rolled back to
+    #[serde(default)]
+    pub pinned: bool, // Update checks skip pinned apps
+    #[serde(default)]
+    pub update_policy: UpdatePolicy,
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     pub fn default(
         name: String,
+        display_name: String,
         path: String,
         version: String,
         image_url: String,
@@ -498,6 +1068,7 @@ impl App {
             installed: false,
             event: AppEvents::default(),
             name,
+            display_name,
             path,
             app_path: String::new(),
             version,
@@ -508,23 +1079,54 @@ impl App {
             has_update,
             update_download_url: None,
             launchable,
-            progress: Arc::new(AtomicI8::new(0)),
+            progress: Progress::default(),
             thread_communication: ThreadCommunication::default(),
             process: Arc::new(AtomicU32::new(0)),
-            installation_data
+            installation_data,
+            source: SourceConfig::default(),
+            checksum: ChecksumStatus::default(),
+            signature_policy: SignaturePolicy::default(),
+            download_control: DownloadControl::default(),
+            previous_version: None,
+            pinned: false,
+            update_policy: UpdatePolicy::default(),
         }
     }
 }
 
 impl App {
+    // Never while it is running (AppEvents::Running) or already installing
+    fn installs_update_automatically(&self, settings: &Settings) -> bool {
+        settings.auto_install_updates
+            && self.installed
+            && self.has_update
+            && self.update_policy.mode == UpdateMode::AutoInstall
+            && self.event == AppEvents::None
+    }
+
+    fn install_update_automatically(&mut self, settings: &Settings, queue: &DownloadQueue) {
+        println!("Installing {} {} automatically", self.name, self.release_data.tag_name);
+        self.event = AppEvents::Downloading;
+        update(self, settings, &self.progress, &self.thread_communication.event_sender, queue);
+    }
+
     #[allow(clippy::too_many_arguments)]
-    pub fn render(&mut self, ui: &mut Ui, theme: &Theme, notifications: &mut VecDeque<Notification>, settings: &mut Settings) {
+    pub fn render(&mut self, ui: &mut Ui, theme: &Theme, notifications: &mut VecDeque<Notification>, settings: &mut Settings, queue: &DownloadQueue) -> Option<CardAction> {
+        let mut action = None;
 
         if let Ok(event) = self.thread_communication.event_receiver.try_recv() {
             match event.0 {
                 AppEvents::Failed => {
                     self.event = AppEvents::None;
-                    let notification = app_installation_failure(&self.name);
+                    let notification = match event.1 {
+                        Some(reason) => app_installation_failure_reason(&self.display_name, &reason),
+                        None => app_installation_failure(&self.display_name),
+                    };
+                    notify(ui.ctx(), notification, notifications);
+                }
+                AppEvents::BadToken => {
+                    self.event = AppEvents::None;
+                    let notification = bad_token_notification();
                     notify(ui.ctx(), notification, notifications);
                 }
                 AppEvents::JavaInstalled => {
@@ -539,7 +1141,10 @@ impl App {
                     self.event = AppEvents::None;
                     update_app_data(self);
                     save_app_data_offline(self);
-                    let notification = app_installation_success(&self.name);
+                    let notification = match &self.previous_version {
+                        Some(previous_version) => app_update_success(&self.display_name, previous_version),
+                        None => app_installation_success(&self.display_name),
+                    };
                     notify(ui.ctx(), notification, notifications);
                 }
                 AppEvents::ManagerInstalled => {
@@ -551,6 +1156,31 @@ impl App {
                     let notification = manager_installation_success();
                     notify(ui.ctx(), notification, notifications);
                 }
+                AppEvents::Cancelled => {
+                    self.event = AppEvents::None;
+                    let notification = app_installation_cancelled(&self.display_name);
+                    notify(ui.ctx(), notification, notifications);
+                }
+                AppEvents::Exited => {
+                    // A manual update may have started while it ran
+                    if self.event == AppEvents::Running {
+                        self.event = AppEvents::None;
+                    }
+                    if self.installs_update_automatically(settings) {
+                        self.install_update_automatically(settings, queue);
+                    }
+                }
+                AppEvents::VersionInstalled => {
+                    self.event = AppEvents::None;
+                    let notification = app_version_installed(&self.display_name, &event.1.unwrap_or_default());
+                    notify(ui.ctx(), notification, notifications);
+                }
+                AppEvents::Verified => {
+                    self.checksum = match event.1 {
+                        Some(sha256) => ChecksumStatus::Verified(sha256),
+                        None => ChecksumStatus::Unpublished,
+                    };
+                }
                 AppEvents::AppInstalled => {
                     println!("App Installed!");
                     self.installed = true;
@@ -558,7 +1188,10 @@ impl App {
                     self.event = AppEvents::None;
                     update_app_data(self);
                     save_app_data_offline(self);
-                    let notification = app_installation_success(&self.name);
+                    let notification = match &self.previous_version {
+                        Some(previous_version) => app_update_success(&self.display_name, previous_version),
+                        None => app_installation_success(&self.display_name),
+                    };
                     notify(ui.ctx(), notification, notifications);
                 }
                 _ => {
@@ -570,19 +1203,103 @@ impl App {
         ui.allocate_ui(Vec2::from([550., 140.]), |ui| {
             ui.horizontal(|ui| {
                 // Image
-                let image = app_image(&self.name);
-                ui.add_sized([100., 100.], image);
-
+                let image = app_image(&self.image_url);
+                let image_response = ui.add_sized([100., 100.], image);
                 let installing = self.event == AppEvents::Downloading || self.event == AppEvents::Extracting;
+                if !self.installation_data.is_manager {
+                    image_response.context_menu(|ui| {
+                        for policy in SignaturePolicy::ALL {
+                            if ui.radio(self.signature_policy == policy, policy.label()).clicked() {
+                                self.signature_policy = policy;
+                                save_app_data_offline(self);
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        ui.menu_button("Updates", |ui| {
+                            let mut update_policy = self.update_policy.clone();
+                            for mode in UpdateMode::ALL {
+                                ui.radio_value(&mut update_policy.mode, mode, mode.label());
+                            }
+                            ui.separator();
+                            for range in [UpdateRange::Any, UpdateRange::SameMajor, UpdateRange::SameMinor] {
+                                let label = range.label();
+                                ui.radio_value(&mut update_policy.range, range, label);
+                            }
+                            let custom = matches!(update_policy.range, UpdateRange::Custom(_));
+                            if ui.radio(custom, "Custom range").clicked() && !custom {
+                                update_policy.range = UpdateRange::Custom(format!("^{}", parse_semver(&self.version)));
+                            }
+                            // Typed into a draft that's only saved on Enter or when the field loses focus
+                            let draft_id = ui.make_persistent_id(("custom_update_range", &self.name));
+                            if let UpdateRange::Custom(requirement) = &mut update_policy.range {
+                                let mut draft = ui.data(|data| data.get_temp::<String>(draft_id)).unwrap_or_else(|| requirement.clone());
+                                let response = ui.text_edit_singleline(&mut draft).on_hover_text("A semver range, Ex: >=2.1, <3");
+                                let draft_range = UpdateRange::Custom(draft.clone());
+                                if response.lost_focus() && draft_range.is_valid() {
+                                    *requirement = draft;
+                                    ui.data_mut(|data| data.remove::<String>(draft_id));
+                                } else {
+                                    ui.data_mut(|data| data.insert_temp(draft_id, draft));
+                                }
+                                if !draft_range.is_valid() {
+                                    ui.colored_label(Color32::RED, "Not a valid range; it won't be saved");
+                                }
+                            } else {
+                                ui.data_mut(|data| data.remove::<String>(draft_id));
+                            }
+                            if !update_policy.range.is_valid() {
+                                ui.colored_label(Color32::RED, "Not a valid range; no updates will be offered");
+                            }
+                            if !update_policy.skipped_versions.is_empty() {
+                                ui.separator();
+                                for version in self.update_policy.skipped_versions.iter() {
+                                    if ui.button(format!("Stop skipping {}", version)).clicked() {
+                                        update_policy.skipped_versions.retain(|skipped| skipped != version);
+                                    }
+                                }
+                            }
+                            if update_policy != self.update_policy {
+                                set_update_policy(self, update_policy);
+                            }
+                        });
+                        if self.has_update && !installing {
+                            let tag = self.release_data.tag_name.clone();
+                            if ui.button(format!("Skip {}", tag)).clicked() {
+                                let mut update_policy = self.update_policy.clone();
+                                update_policy.skip(&tag);
+                                set_update_policy(self, update_policy);
+                                ui.close_menu();
+                            }
+                        }
+                        if ui.button("Versions...").clicked() {
+                            action = Some(CardAction::ShowVersions);
+                            ui.close_menu();
+                        }
+                        // Never swapped out from under the running app
+                        if let Some(previous_version) = self.previous_version.clone().filter(|_| self.installed && !installing && self.event != AppEvents::Running) {
+                            if ui.button(format!("Roll back to {}", previous_version)).clicked() {
+                                let notification = match roll_back(self) {
+                                    Ok(()) => app_rolled_back(&self.display_name, &previous_version),
+                                    Err(e) => app_rollback_failure(&self.display_name, &e.to_string()),
+                                };
+                                notify(ui.ctx(), notification, notifications);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
                 ui.vertical(|ui| {
                    ui.horizontal(|ui| {
                        let name = if self.installed {
-                           format!("{} {}", &self.name, &self.version)
+                           format!("{} {}", &self.display_name, &self.version)
                        } else {
-                           self.name.clone()
+                           self.display_name.clone()
                        };
                        // Name
-                       ui.add_sized([320., 40.], egui::Label::new(RichText::new(name).color(theme.text).strong()));
+                       if ui.add_sized([320., 40.], egui::Label::new(RichText::new(name).color(theme.text).strong()).sense(Sense::click())).on_hover_text("Details and release notes").clicked() {
+                           action = Some(CardAction::ShowDetails);
+                       }
 
                        // Action Button
                        let action_button_text = if self.installed {
@@ -592,7 +1309,12 @@ impl App {
                            }
                        } else {
                            match self.event {
-                               AppEvents::Downloading => "Downloading".to_string(),
+                               AppEvents::Downloading if self.download_control.is_paused() => "Paused".to_string(),
+                               AppEvents::Downloading if queue.job_for(&self.name).is_some_and(|job| job.status == JobStatus::Queued) => "Queued".to_string(),
+                               AppEvents::Downloading => match self.progress.fraction() {
+                                   Some(fraction) => format!("{}%", (fraction * 100.) as u8),
+                                   None => format_size(self.progress.bytes_done()),
+                               },
                                AppEvents::Extracting => "Extracting".to_string(),
                                _ => "Install".to_string(),
                            }
@@ -613,11 +1335,11 @@ impl App {
 
                                        if ui.add_sized([45., 40.], egui::Button::new(RichText::new("Update").text_style(notification_font()).color(theme.text)).fill(theme.button)).clicked() {
                                            self.event = AppEvents::Downloading;
-                                           update(&self, &self.progress, &self.thread_communication.event_sender);
+                                           update(self, settings, &self.progress, &self.thread_communication.event_sender, queue);
                                        }
                                    } else if ui.add_sized([100., 40.], egui::Button::new(RichText::new("Update").color(theme.text)).fill(theme.button)).clicked() {
                                        self.event = AppEvents::Downloading;
-                                       update(&self, &self.progress, &self.thread_communication.event_sender);
+                                       update(self, settings, &self.progress, &self.thread_communication.event_sender, queue);
                                    }
                                } else if self.installation_data.launchable && ui.add_sized([100., 40.], egui::Button::new(RichText::new(action_button_text).color(theme.text)).fill(theme.button)).clicked() {
                                    if self.event == AppEvents::Running {
@@ -630,7 +1352,7 @@ impl App {
                            } else {
                                if ui.add_sized([100., 40.], egui::Button::new(RichText::new(action_button_text).color(theme.text)).fill(theme.button)).clicked() {
                                    self.event = AppEvents::Downloading;
-                                   download_application(self, &self.progress, &self.thread_communication.event_sender);
+                                   download_application(self, settings, &self.progress, &self.thread_communication.event_sender, queue);
                                }
                            }
                        });
@@ -639,7 +1361,12 @@ impl App {
                        // Description
 
                        ui.vertical(|ui| {
-                           ui.add_sized([320., 20.], egui::Label::new(RichText::new(&self.github_data.description).color(theme.text).text_style(notification_font())));
+                           let description = if installing && self.progress.phase() != Phase::Idle {
+                               self.progress.describe()
+                           } else {
+                               self.github_data.description.clone()
+                           };
+                           ui.add_sized([320., 20.], egui::Label::new(RichText::new(description).color(theme.text).text_style(notification_font())));
 
                            // Tags
                            render_tags(self, ui);
@@ -647,31 +1374,60 @@ impl App {
 
                        // Action Button / Progress Bar
                        if !installing {
-                           if self.installed {
-                               if ui.add_sized([100., 40.], egui::Button::new(RichText::new("Uninstall").color(theme.text)).fill(theme.button)).clicked() {
-                                   let path_str = format!("openlightsmanager/apps/{}/", self.name);
-                                   let path = Path::new(&path_str);
-                                   let executable_path_str = get_full_path_str(&self.name, &self.installation_data.app_path);
-                                   if path.exists() {
-                                       fs::remove_dir_all(path).unwrap();
-                                       if executable_path_str == settings.jvm_path {
-                                           settings.jvm_path.clear();
-                                           settings.save_settings();
-                                       }
+                           // A running app's files may be locked, and it shouldn't lose them while open
+                           let uninstall = egui::Button::new(RichText::new("Uninstall").color(theme.text)).fill(theme.button);
+                           if self.installed && ui.add_enabled_ui(self.event != AppEvents::Running, |ui| ui.add_sized([100., 40.], uninstall)).inner.clicked() {
+                               let dirs = AppDirs::new(&self.name);
+                               if dirs.app.exists() {
+                                   let executable_path_str = get_full_path_str(&self.name, &self.installation_data.resolve().app_path);
+                                   if executable_path_str == settings.jvm_path {
+                                       settings.jvm_path.clear();
+                                       settings.save_settings();
                                    }
-                                   self.installed = false;
-                                   save_app_data_offline(&self);
+                               }
+                               // The version kept for rollback goes too
+                               match dirs.remove_all() {
+                                   Ok(()) => {
+                                       self.installed = false;
+                                       self.checksum = ChecksumStatus::Unknown;
+                                       self.previous_version = None;
+                                       save_app_data_offline(self);
+                                   }
+                                   // Whatever is left stays listed as installed so it can be removed again
+                                   Err(e) => notify(ui.ctx(), app_uninstall_failure(&self.display_name, &e.to_string()), notifications),
                                }
                            }
+                       } else if self.event == AppEvents::Downloading {
+                           // Pause / Resume / Cancel
+                           ui.ctx().request_repaint_after(Duration::from_millis(10));
+                           let paused = self.download_control.is_paused();
+                           ui.add_enabled_ui(!self.download_control.is_cancelled(), |ui| {
+                               let pause_text = if paused { "Resume" } else { "Pause" };
+                               if ui.add_sized([48., 30.], egui::Button::new(RichText::new(pause_text).text_style(notification_font()).color(theme.text)).fill(theme.button)).clicked() {
+                                   if paused {
+                                       self.download_control.resume();
+                                   } else {
+                                       self.download_control.pause();
+                                   }
+                               }
+                               if ui.add_sized([48., 30.], egui::Button::new(RichText::new("Cancel").text_style(notification_font()).color(theme.text)).fill(theme.button)).clicked() {
+                                   self.download_control.cancel();
+                               }
+                           });
                        } else {
-                           let prgs = self.progress.load(Ordering::Relaxed);
                            ui.ctx().request_repaint_after(Duration::from_millis(10));
-                           ui.add_sized([100., 30.], ProgressBar::new(prgs as f32 / 100.));
+                           // Archives that don't declare their size get a bar that just animates
+                           let bar = match self.progress.fraction() {
+                               Some(fraction) => ProgressBar::new(fraction),
+                               None => ProgressBar::new(0.).animate(true),
+                           };
+                           ui.add_sized([100., 30.], bar);
                        }
                    });
                 });
             });
         });
+        action
     }
 }
 
@@ -688,6 +1444,10 @@ fn render_tags(app: &App, ui: &mut Ui) {
             ui.add_sized(tag_size, egui::Button::new(RichText::new("Library").text_style(notification_font()).color(Color32::BLACK).background_color(Color32::LIGHT_BLUE)).fill(Color32::LIGHT_BLUE).rounding(Rounding::from(16.)).selected(true));
         }
 
+        if app.pinned {
+            ui.add_sized(tag_size, egui::Button::new(RichText::new("Pinned").text_style(notification_font()).color(Color32::BLACK).background_color(Color32::LIGHT_GRAY)).fill(Color32::LIGHT_GRAY).rounding(Rounding::from(16.)).selected(true));
+        }
+
         if app.installation_data.is_manager {
             ui.add_sized(tag_size, egui::Button::new(RichText::new("Manager").text_style(notification_font()).color(Color32::BLACK).background_color(Color32::RED)).fill(Color32::RED).rounding(Rounding::from(16.)).selected(true));
         } else if !app.installation_data.is_library && !app.launchable {
@@ -696,53 +1456,87 @@ fn render_tags(app: &App, ui: &mut Ui) {
     });
 }
 
-fn get_full_path_str(name: &String, executable: &String) -> String {
-    let path_str = format!("openlightsmanager/apps/{}{}", name, executable);
-    let path = Path::new(&path_str);
-    let full_path = fs::canonicalize(path).unwrap();
-    full_path.to_string_lossy().to_string()
-}
-
-fn app_image(name: &String) -> Image<'_> {
-    match name.as_str() {
-        "OpenLightsCore" => {
-            Image::new(egui::include_image!("../assets/OpenLightsCore.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        "OpenLightsManager" => {
-            Image::new(egui::include_image!("../assets/OpenLightsManager.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        "BeatMaker" => {
-            Image::new(egui::include_image!("../assets/BeatMaker.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        "graalvm-ce-builds" => {
-            Image::new(egui::include_image!("../assets/graalvm-ce-builds.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        "BeatFileEditor" => {
-            Image::new(egui::include_image!("../assets/BeatFileEditor.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        "Christmas-Jukebox" => {
-            Image::new(egui::include_image!("../assets/Christmas-Jukebox.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
-        },
-        _ => {
-            Image::new(egui::include_image!("../assets/Unknown.png"))
-                .fit_to_exact_size(Vec2 {x: 100., y: 100.})
-                .rounding(Rounding::from(8.))
+// One line on the versions screen, with its buttons on the right
+fn version_row(ui: &mut Ui, theme: &Theme, version: &str, status: &str, buttons: impl FnOnce(&mut Ui)) {
+    ui.horizontal(|ui| {
+        ui.add_sized([200., 30.], egui::Label::new(RichText::new(version).color(theme.text).strong()));
+        ui.add_sized([120., 30.], egui::Label::new(RichText::new(status).color(theme.text).text_style(notification_font())));
+        buttons(ui);
+    });
+}
+
+// An update shows the notes of every version it skips; otherwise the installed version's, or the latest for apps not installed yet
+fn notes_to_show(app: &App, releases: Option<&[ReleaseData]>, prerelease: bool) -> (String, Vec<ReleaseData>) {
+    if app.installed && app.has_update {
+        let skipped = releases
+            .map(|releases| releases_between(releases, &app.version, &app.release_data.tag_name, prerelease))
+            .filter(|skipped| !skipped.is_empty())
+            .unwrap_or_else(|| vec![app.release_data.clone()]);
+        (format!("What's new since {}", app.version), skipped)
+    } else if app.installed {
+        let installed = releases
+            .and_then(|releases| releases.iter().find(|release| release.tag_name == app.version))
+            .or(Some(&app.release_data).filter(|release| release.tag_name == app.version))
+            .cloned();
+        (format!("Release notes for {}", app.version), installed.into_iter().collect())
+    } else {
+        (format!("Release notes for {}", app.release_data.tag_name), vec![app.release_data.clone()])
+    }
+}
+
+fn release_notes(ui: &mut Ui, theme: &Theme, release: &ReleaseData) {
+    ui.horizontal(|ui| {
+        let title = release.name.clone().filter(|name| !name.trim().is_empty()).unwrap_or(release.tag_name.clone());
+        ui.label(RichText::new(title).color(theme.text).strong());
+        let mut details = release.tag_name.clone();
+        if let Some(published_at) = release.published_at {
+            details.push_str(&format!(" - {}", published_at.with_timezone(&Local).format("%m/%d/%Y")));
+        }
+        ui.label(RichText::new(details).color(theme.text).text_style(notification_font()));
+        if let Some(html_url) = &release.html_url {
+            ui.hyperlink_to(RichText::new("View on Github").text_style(notification_font()), html_url);
         }
+    });
+    match release.body.as_deref().map(str::trim).filter(|body| !body.is_empty()) {
+        Some(body) => render_markdown(ui, body, theme.text),
+        None => {
+            ui.label(RichText::new("No release notes were published.").color(theme.text).text_style(notification_font()));
+        }
+    }
+}
+
+fn describe_job(job: &DownloadJob) -> String {
+    match &job.status {
+        JobStatus::Queued => "Waiting for another download to finish".to_string(),
+        JobStatus::Finished => "Finished".to_string(),
+        JobStatus::Cancelled => "Cancelled".to_string(),
+        JobStatus::Failed(reason) => format!("Failed: {}", reason),
+        JobStatus::Active if job.control.is_paused() => format!("Paused at {}", format_size(job.progress.bytes_done())),
+        JobStatus::Active => job.progress.describe(),
+    }
+}
+
+fn get_full_path_str(name: &str, executable: &str) -> String {
+    let path_str = format!("openlightsmanager/apps/{}{}", name, executable);
+    // A half-installed app may be missing its executable
+    match fs::canonicalize(Path::new(&path_str)) {
+        Ok(full_path) => full_path.to_string_lossy().to_string(),
+        Err(_) => path_str,
     }
 }
 
+fn app_image(image_url: &str) -> Image<'_> {
+    let image = if Path::new(image_url).exists() {
+        Image::new(format!("file://{}", image_url))
+    } else {
+        Image::new(egui::include_image!("../assets/Unknown.png"))
+    };
+    image
+        .fit_to_exact_size(Vec2 {x: 100., y: 100.})
+        .rounding(Rounding::from(8.))
+        .sense(egui::Sense::click()) // Right click opens the app's options
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GithubData {
     pub(crate) description: String,
@@ -750,18 +1544,30 @@ pub struct GithubData {
     pub(crate) releases_url: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ReleaseData {
     pub tag_name: String,
     pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
     pub id: i32,
     pub assets: Vec<AssetData>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>, // The release notes, in Markdown
+    #[serde(default)]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub html_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AssetData {
     pub size: i32,
     pub browser_download_url: String,
+    #[serde(default)]
+    pub digest: Option<String>, // Ex: sha256:2cf24dba...
 }
 
 #[derive(Clone)]
@@ -0,0 +1,89 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+
+const CACHE_DIR: &str = "openlightsmanager/cache/";
+
+// A response body saved alongside the validators Github gave us for it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub link: Option<String>, // Pagination has to keep working off a 304
+    pub body: String,
+}
+
+impl CachedResponse {
+    // Makes the request conditional so an unchanged response comes back as a 304
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = match &self.etag {
+            Some(etag) => request.header(IF_NONE_MATCH, etag),
+            None => request,
+        };
+        match &self.last_modified {
+            Some(last_modified) => request.header(IF_MODIFIED_SINCE, last_modified),
+            None => request,
+        }
+    }
+}
+
+pub fn load_cached(url: &str) -> Option<CachedResponse> {
+    let file = File::open(cache_path(url)).ok()?;
+    let reader = BufReader::new(file);
+    let cached: CachedResponse = serde_json::from_reader(reader).ok()?;
+    // Guards against two URLs sanitizing to the same file name
+    if cached.url == url {
+        Some(cached)
+    } else {
+        None
+    }
+}
+
+pub fn store_cached(url: &str, headers: &HeaderMap, body: &str) {
+    let etag = header_string(headers, ETAG.as_str());
+    let last_modified = header_string(headers, LAST_MODIFIED.as_str());
+    if etag.is_none() && last_modified.is_none() {
+        return; // Nothing to validate against later
+    }
+
+    let cached = CachedResponse {
+        url: url.to_string(),
+        etag,
+        last_modified,
+        link: header_string(headers, LINK.as_str()),
+        body: body.to_string(),
+    };
+    if let Err(e) = fs::create_dir_all(CACHE_DIR) {
+        println!("Failed to create the cache folder: {}", e);
+        return;
+    }
+    match File::create(cache_path(url)) {
+        Ok(file) => {
+            let writer = BufWriter::new(file);
+            if let Err(e) = serde_json::to_writer(writer, &cached) {
+                println!("Failed to cache {}: {}", url, e);
+            }
+        }
+        Err(e) => println!("Failed to cache {}: {}", url, e),
+    }
+}
+
+fn cache_path(url: &str) -> PathBuf {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let file_name: String = without_scheme
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    PathBuf::from(CACHE_DIR).join(format!("{}.json", file_name))
+}
+
+fn header_string(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
@@ -1,69 +1,99 @@
-use std::{fs, io, thread};
-use std::env::current_dir;
+use std::{fmt, fs, io, thread};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env::{consts, current_dir};
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Write};
-use std::path::Path;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicI8, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::Sender;
 
-use chrono::Utc;
-use futures_util::StreamExt;
-use reqwest::header;
+use chrono::{DateTime, Utc};
+use reqwest::{header, StatusCode};
 use semver::{Version, VersionReq};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use tokio::runtime::Runtime;
-use zip::ZipArchive;
 
-use crate::gui::{App, GithubData, Notification, ReleaseData, ThreadCommunication};
-use crate::notifications::{launched_application, launched_application_missing_java, rate_limit_notification};
+use crate::archive::{extract_archive, is_archive_name, ArchiveError, ExtractionLimits};
+use crate::asset_match::{asset_filename, select_asset, AssetMatchError, AssetRules};
+use crate::catalog::Catalog;
+use crate::download::{get_file, DownloadControl};
+use crate::download_queue::{DownloadQueue, JobKind, JobStatus};
+use crate::checksum::{check_sha256, expected_sha256, ChecksumStatus};
+use crate::graphql::{fetch_repositories, GITHUB_GRAPHQL_URL};
+use crate::gui::{App, AssetData, GithubData, Notification, ReleaseData, ThreadCommunication};
+use crate::http_cache::{load_cached, store_cached};
+use crate::notifications::{bad_token_notification, github_request_failed, github_unreachable_notification, launched_application, launched_application_missing_java, rate_limit_notification};
+use crate::progress::{Phase, Progress};
+use crate::rate_limit::RateLimit;
 use crate::settings::Settings;
+use crate::signature::{verify_download, SignatureError, SignaturePolicy};
+use crate::source::{allow_any, ReleaseQuery, SourceConfig};
+use crate::staging::{AppDirs, APPS_DIR};
+use crate::tasks::block_on;
+use crate::update_policy::UpdatePolicy;
 
-const GITHUB_REPOS: [&str; 6] = ["Open-Lights/OpenLightsCore", "Open-Lights/OpenLightsManager", "Open-Lights/BeatMaker", "Open-Lights/Christmas-Jukebox", "Open-Lights/BeatFileEditor", "graalvm/graalvm-ce-builds"];
-
-pub fn gather_app_data(prerelease: bool, settings: &mut Settings) -> (Vec<App>, Option<Notification>) { // TODO Fix an issue with prerelease being true and not loading stable releases
+pub fn gather_app_data(catalog: &Catalog, prerelease: bool, settings: &mut Settings) -> (Vec<App>, Option<Notification>) {
     let mut vector = Vec::new();
     let mut checked_github = false;
-    for project_str in GITHUB_REPOS {
-        let project = String::from(project_str);
-        let parts: Vec<&str> = project.split('/').collect();
-        let project_name = parts.get(1).unwrap_or(&"");
+    // Once Github refuses a request, the remaining apps are loaded from disk only
+    let mut github_error: Option<GithubError> = None;
+    for entry in &catalog.apps {
+        let project = entry.repo.clone();
+        let project_name = entry.name();
         let format = format!("openlightsmanager/appdata/{}.json", project_name);
         let path = Path::new(format.as_str());
         println!("Path: {}", format);
-        if path.exists() {
-            let file = OpenOptions::new()
-                .read(true)
-                .open(path)
-                .unwrap();
-            let reader = BufReader::new(file);
-            let mut app: App = serde_json::from_reader(reader).unwrap();
-            app.installation_data = get_installation_data(&app);
+        // Unreadable appdata is fetched again as if the app were new
+        let saved = if path.exists() { read_app_data(path) } else { None };
+        if let Some(mut app) = saved {
+            if !entry.installation_data.supports_current_platform() {
+                println!("{} has no build for {} {}", project_name, consts::OS, consts::ARCH);
+                continue;
+            }
+            app.display_name = entry.display_name.clone();
+            app.image_url = entry.icon_path().to_string_lossy().to_string();
+            app.installation_data = entry.installation_data.clone();
+            app.source = entry.source.clone();
             if app.installation_data.is_manager {
                 app.installed = true;
                 // TODO Write the first manager json to file
             }
-            check_for_updates(&mut app, prerelease, settings, false);
+            if github_error.is_none() {
+                if let Err(error) = check_for_updates(&mut app, prerelease, settings, false) {
+                    println!("Failed to check {} for updates: {}", project_name, error);
+                    github_error = Some(error);
+                }
+            }
             vector.push(app);
         } else {
-            fs::create_dir_all("openlightsmanager/appdata/").unwrap();
-            if should_check_github(&settings) {
+            if let Err(e) = fs::create_dir_all("openlightsmanager/appdata/") {
+                println!("Failed to create the appdata folder: {}", e);
+            }
+            if !entry.installation_data.supports_current_platform() {
+                println!("{} has no build for {} {}", project_name, consts::OS, consts::ARCH);
+                continue;
+            }
+            if github_error.is_none() && should_check_source(settings, &entry.source) {
                 checked_github = true;
-                let latest_data;
-                let latest_data_pre = get_latest_version_data(&project, true, false);
-                // Rate limited
-                if let Some(notification) = latest_data_pre.2 {
-                    println!("Github has rate limited us!");
-                    return (vector, Some(notification));
-                }
-
-                if let Some(release_data) = latest_data_pre.1 {
-                    latest_data = (latest_data_pre.0, release_data);
-                } else {
-                    println!("No valid release or prerelease was found for {}\n", project_name);
-                    continue;
-                }
+                let query = ReleaseQuery { prerelease, max_pages: settings.release_page_limit, list_all: false, allows: &allow_any };
+                let latest_data = match entry.source.release_source().releases(&project, &query, &settings.github_token, &mut settings.rate_limit) {
+                    // Apps that have only published prereleases are still listed on the stable channel
+                    Ok((github_data, release_data)) => match select_release(&release_data, prerelease).or_else(|| select_release(&release_data, true)) {
+                        Some(release) => (github_data, release),
+                        None => {
+                            println!("No valid release or prerelease was found for {}\n", project_name);
+                            continue;
+                        }
+                    },
+                    Err(error) => {
+                        println!("Failed to fetch {}: {}", project_name, error);
+                        github_error = Some(error);
+                        continue;
+                    }
+                };
                 let version = parse_semver(&latest_data.1.tag_name);
                 let mut app = App {
                     installed: false,
@@ -71,23 +101,29 @@ pub fn gather_app_data(prerelease: bool, settings: &mut Settings) -> (Vec<App>,
                     path: path.to_string_lossy().to_string(),
                     app_path: String::new(),
                     name: project_name.to_string(),
+                    display_name: entry.display_name.clone(),
                     version: version.to_string(),
-                    image_url: format!("../assets/{}.png", project_name),
+                    image_url: entry.icon_path().to_string_lossy().to_string(),
                     github_repo: project,
                     github_data: latest_data.0,
                     release_data: latest_data.1,
                     has_update: false,
                     update_download_url: None,
                     launchable: false,
-                    progress: Arc::new(AtomicI8::new(0)),
+                    progress: Progress::default(),
                     thread_communication: ThreadCommunication::default(),
                     process: Arc::new(AtomicU32::new(0)),
-                    installation_data: InstallationData::default(),
+                    installation_data: entry.installation_data.clone(),
+                    source: entry.source.clone(),
+                    checksum: ChecksumStatus::default(),
+                    signature_policy: SignaturePolicy::default(),
+                    download_control: DownloadControl::default(),
+                    previous_version: None,
+                    pinned: false,
+                    update_policy: UpdatePolicy::default(),
                 };
-                let installation_data = get_installation_data(&app);
-                app.app_path = installation_data.app_path.clone();
-                app.launchable = installation_data.launchable;
-                app.installation_data = installation_data;
+                app.app_path = app.installation_data.resolve().app_path;
+                app.launchable = app.installation_data.launchable;
                 if app.installation_data.is_manager {
                     app.installed = true;
                     // TODO Write the first manager json to file
@@ -99,118 +135,242 @@ pub fn gather_app_data(prerelease: bool, settings: &mut Settings) -> (Vec<App>,
     }
     if checked_github {
         set_checked_for_update(settings);
+    } else if github_error.is_some() {
+        // Keep what Github told us about the rate limit
+        settings.save_settings();
     }
-    (vector, None)
-}
-
-// Strict means it must be stable if prefer_stable is true
-// If not strict and no stable builds are found, the latest unstable build is provided
-pub fn get_latest_version_data(project: &String, prefer_stable: bool, strict: bool) -> (GithubData, Option<ReleaseData>, Option<Notification>) {
-    let url = format!("https://api.github.com/repos/{}", project);
-    println!("{}", &url);
-    let rt = Runtime::new().unwrap();
-    let response = rt.block_on(get_json(&url.to_owned()));
-
-    if let Ok(github_data) = serde_json::from_str::<GithubData>(&response) {
-        let release_repo_url = &github_data.releases_url;
-        let modified_repo_url = release_repo_url.replace("{/id}", "");
-        let response_release = rt.block_on(get_json(&modified_repo_url));
-        println!("{}", &modified_repo_url);
-        let release_response: Result<Vec<ReleaseData>, serde_json::Error> = serde_json::from_str(&response_release);
-        if let Ok(release_data) = release_response {
-            if !release_data.is_empty() {
-                if prefer_stable {
-                    for release in release_data.clone() {
-                        if !release.prerelease {
-                            return (github_data, Some(release), None);
-                        }
-                    }
+    (vector, github_error.map(|error| error.notification()))
+}
 
-                    if !strict {
-                        return (github_data, Some(release_data[0].clone()), None);
-                    }
-                } else {
-                    return (github_data, Some(release_data[0].clone()), None);
-                }
-            }
-        }
-        return (github_data, None, None); // No releases are present
-    }
-    // Rate Limited output
-    rate_limited_output()
+pub fn get_latest_version_data(source: &SourceConfig, project: &str, query: &ReleaseQuery<'_>, token: &str, rate_limit: &mut RateLimit) -> Result<(GithubData, Option<ReleaseData>), GithubError> {
+    let (github_data, release_data) = source.release_source().releases(project, query, token, rate_limit)?;
+    let release = select_release_where(&release_data, query.prerelease, query.allows);
+    Ok((github_data, release)) // None when nothing matches the channel
 }
 
-pub fn get_version_data(project: String, id: i32) -> (GithubData, Option<ReleaseData>, Option<Notification>) {
-    let url = format!("https://api.github.com/repos/{}", project);
-    let rt = Runtime::new().unwrap();
-    let response = rt.block_on(get_json(&url.to_owned()));
+// The newest release by semver, ignoring drafts
+// Prereleases are only considered on the prerelease channel, which still gets stable releases that are newer
+// Tags that aren't semver count as 0.0.0, so among those the first listed (newest) one wins
+pub fn select_release(release_data: &[ReleaseData], prerelease: bool) -> Option<ReleaseData> {
+    select_release_where(release_data, prerelease, |_| true)
+}
 
-    if let Ok(github_data) = serde_json::from_str::<GithubData>(&response) {
-        let release_repo_url = &github_data.releases_url;
-        let modified_repo_url = release_repo_url.replace("{/id}", format!("/{}", id).as_str());
-        let response_release = rt.block_on(get_json(&modified_repo_url.to_owned()));
-        let release_data: crate::gui::ReleaseData = serde_json::from_str(&response_release).unwrap();
-        (github_data, Some(release_data), None)
-    } else {
-        // Rate limited
-        rate_limited_output()
+// Like select_release, among the releases an app's update policy allows
+pub fn select_release_where(release_data: &[ReleaseData], prerelease: bool, allows: impl Fn(&ReleaseData) -> bool) -> Option<ReleaseData> {
+    release_data.iter()
+        .filter(|release| !release.draft)
+        .filter(|release| prerelease || !is_prerelease(release))
+        .filter(|release| allows(release))
+        .rev()
+        .max_by(|a, b| parse_semver(&a.tag_name).cmp(&parse_semver(&b.tag_name)))
+        .cloned()
+}
+
+// What an update from the current version to the target jumps over, the target included, newest first
+// Prereleases in between are left out on the stable channel
+pub fn releases_between(release_data: &[ReleaseData], current: &str, target: &str, prerelease: bool) -> Vec<ReleaseData> {
+    let current = parse_semver(current);
+    let target_ver = parse_semver(target);
+    let mut between: Vec<ReleaseData> = release_data.iter()
+        .filter(|release| !release.draft)
+        .filter(|release| {
+            let version = parse_semver(&release.tag_name);
+            release.tag_name == target || ((prerelease || !is_prerelease(release)) && version > current && version <= target_ver)
+        })
+        .cloned()
+        .collect();
+    between.sort_by_key(|release| Reverse(parse_semver(&release.tag_name)));
+    between
+}
+
+// Some projects tag prereleases like 2.0.0-beta.1 without marking them on Github
+fn is_prerelease(release: &ReleaseData) -> bool {
+    release.prerelease || !is_stable(parse_semver(&release.tag_name))
+}
+
+// Every release of an app, newest first, for picking a specific version to install
+pub fn get_all_versions(target: &UpdateTarget, settings: &mut Settings) -> Result<Vec<ReleaseData>, GithubError> {
+    if !should_check_source(settings, &target.source) {
+        return Err(GithubError::RateLimited(settings.rate_limit.resumes_at()));
     }
+    let query = ReleaseQuery { prerelease: true, max_pages: settings.release_page_limit, list_all: true, allows: &allow_any };
+    let (_, mut release_data) = target.source.release_source().releases(&target.github_repo, &query, &settings.github_token, &mut settings.rate_limit)?;
+    release_data.retain(|release| !release.draft);
+    // Stable, so tags that aren't semver keep their listing order
+    release_data.sort_by_key(|release| Reverse(parse_semver(&release.tag_name)));
+    Ok(release_data)
 }
 
-pub fn should_check_github(settings: &Settings) -> bool {
+// Each app costs one request for the repository and at least one for its releases
+pub const REQUESTS_PER_APP: usize = 2;
+
+// Other sources don't share Github's rate limit
+fn should_check_source(settings: &Settings, source: &SourceConfig) -> bool {
+    !source.is_github() || should_check_github(settings, REQUESTS_PER_APP)
+}
+
+pub fn should_check_github(settings: &Settings, requests: usize) -> bool {
     if !settings.override_rate_limit {
-        let current_time = Utc::now();
-        let last_check = settings.last_github_check.unwrap();
-        let time_diff = current_time.signed_duration_since(last_check);
-        let min_per_check = minutes_between_gh_checks(!settings.github_token.is_empty());
-        let required_time_diff = chrono::Duration::minutes(min_per_check);
-        println!("Waited time: {}; Required time: {}", time_diff.num_minutes(), required_time_diff.num_minutes());
-        time_diff > required_time_diff
+        let allowed = settings.rate_limit.allows(requests);
+        println!("Requests needed: {}; Remaining: {:?}", requests, settings.rate_limit.remaining);
+        allowed
     } else {
         true
     }
 }
 
-fn minutes_between_gh_checks(authed: bool) -> i64 {
-    let minutes_in_hour = 60;
-    let requests_per_hour = if authed { 5000 } else { 60 };
-    let requests_per_check = GITHUB_REPOS.len() as i32 * 2;
-    let unrounded = (minutes_in_hour / requests_per_hour) as f32 * requests_per_check as f32;
-    unrounded.ceil() as i64
+#[derive(Debug, Clone, PartialEq)]
+pub enum GithubError {
+    Unauthorized(u16), // 401/403 that isn't a rate limit, so the token was rejected
+    RateLimited(Option<DateTime<Utc>>), // When requests may resume, if Github told us
+    Http(u16),
+    InvalidResponse(String),
+    Network(String),
+    Io(String), // Reading or writing local files, which asking again won't fix
+    Incomplete { expected: u64, actual: u64 }, // The download ended up a different size than the release listed
+    Cancelled, // Stopped by the user
 }
 
-fn rate_limited_output() -> (GithubData, Option<ReleaseData>, Option<Notification>) {
-    // Rate Limited output
-    let fake_github_data = GithubData {
-        description: "Rate limited".to_string(),
-        archived: false,
-        releases_url: "Unknown".to_string(),
-    };
-    let notification = rate_limit_notification();
-    (fake_github_data, None, Some(notification))
+impl fmt::Display for GithubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GithubError::Unauthorized(status) => write!(f, "Github rejected the token (HTTP {})", status),
+            GithubError::RateLimited(Some(resume)) => write!(f, "Github rate limit exceeded until {}", resume),
+            GithubError::RateLimited(None) => write!(f, "Github rate limit exceeded"),
+            GithubError::Http(status) => write!(f, "Github responded with HTTP {}", status),
+            GithubError::InvalidResponse(error) => write!(f, "Github sent an unexpected response: {}", error),
+            GithubError::Network(error) => write!(f, "Failed to reach Github: {}", error),
+            GithubError::Io(error) => write!(f, "Failed to read or write a local file: {}", error),
+            GithubError::Incomplete { expected, actual } => write!(f, "The download is {} bytes but should be {}", actual, expected),
+            GithubError::Cancelled => write!(f, "The download was cancelled"),
+        }
+    }
+}
+
+impl GithubError {
+    pub fn notification(&self) -> Notification {
+        match self {
+            GithubError::Unauthorized(_) => bad_token_notification(),
+            GithubError::RateLimited(resume) => rate_limit_notification(*resume),
+            GithubError::Network(_) => github_unreachable_notification(),
+            GithubError::Http(_) | GithubError::InvalidResponse(_) | GithubError::Io(_) | GithubError::Incomplete { .. } | GithubError::Cancelled => github_request_failed(&self.to_string()),
+        }
+    }
+}
+
+pub(crate) fn github_request(client: &reqwest::Client, url: &str, token: &str) -> reqwest::RequestBuilder {
+    let request = client.get(url).header(header::USER_AGENT, "Open-Lights-Manager");
+    let token = token.trim();
+    if token.is_empty() {
+        request
+    } else {
+        request.bearer_auth(token)
+    }
+}
+
+pub(crate) fn check_status(response: &reqwest::Response, token: &str, rate_limit: &mut RateLimit) -> Result<(), GithubError> {
+    rate_limit.update(response.headers());
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS || (status == StatusCode::FORBIDDEN && rate_limit.is_limited()) {
+        // Github also answers 403 once the rate limit is used up
+        return Err(GithubError::RateLimited(rate_limit.resumes_at()));
+    }
+    if (status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN) && sent_github_token(response.url(), token) {
+        return Err(GithubError::Unauthorized(status.as_u16()));
+    }
+    if !status.is_success() {
+        return Err(GithubError::Http(status.as_u16()));
+    }
+    Ok(())
+}
+
+// Only then can a refusal be blamed on the token; other hosts and Github's asset CDN never see it
+fn sent_github_token(url: &reqwest::Url, token: &str) -> bool {
+    !token.trim().is_empty() && url.host_str() == Some("api.github.com")
 }
 
-async fn get_json(url: &String) -> String {
+// file:// URLs, Ex: from a release index on a mounted share, are read from disk instead of downloaded
+pub(crate) fn local_file_path(url: &str) -> Result<Option<PathBuf>, GithubError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "file" => parsed.to_file_path()
+            .map(Some)
+            .map_err(|_| GithubError::Io(format!("{} is not a local file", url))),
+        _ => Ok(None),
+    }
+}
+
+pub(crate) async fn get_json(url: &str, token: &str, rate_limit: &mut RateLimit) -> Result<String, GithubError> {
+    let (body, _) = get_json_page(url, token, rate_limit).await?;
+    Ok(body)
+}
+
+// Also returns the URL of the next page from the Link header, if there is one
+pub(crate) async fn get_json_page(url: &str, token: &str, rate_limit: &mut RateLimit) -> Result<(String, Option<String>), GithubError> {
+    if rate_limit.is_limited() {
+        return Err(GithubError::RateLimited(rate_limit.resumes_at()));
+    }
     let client = reqwest::Client::new();
-    let resp = client.get(url)
-        .header(header::USER_AGENT, "Open-Lights-Manager")
+    let cached = load_cached(url);
+    let mut request = github_request(&client, url, token);
+    if let Some(cached) = &cached {
+        request = cached.apply(request);
+    }
+    let response = request
         .send()
         .await
-        .expect("Failed to request json")
-        .text()
-        .await;
-    resp.unwrap()
+        .map_err(|e| GithubError::Network(e.to_string()))?;
+
+    // Nothing changed since the cached copy, and Github doesn't count this request
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            rate_limit.update(response.headers());
+            println!("{} not modified, using cache", url);
+            let next = cached.link.as_deref().and_then(next_page_url);
+            return Ok((cached.body, next));
+        }
+    }
+
+    check_status(&response, token, rate_limit)?;
+    let headers = response.headers().clone();
+    let body = response.text().await.map_err(|e| GithubError::Network(e.to_string()))?;
+    store_cached(url, &headers, &body);
+    let next = headers.get(header::LINK).and_then(|link| link.to_str().ok()).and_then(next_page_url);
+    Ok((body, next))
 }
 
-pub fn save_apps_data(mut apps: Vec<App>, prerelease: bool, settings: &mut Settings) {
-    for mut app in apps.iter_mut() {
-        save_app_data(&mut app, prerelease, settings);
-    }
+// Ex: <https://api.github.com/repositories/1/releases?page=2>; rel="next", <...>; rel="last"
+pub(crate) fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        let is_next = params.split(';').any(|param| matches!(param.trim(), "rel=\"next\"" | "rel=next"));
+        if is_next {
+            Some(url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+        } else {
+            None
+        }
+    })
 }
 
-pub fn save_app_data(app: &mut App, prerelease: bool, settings: &mut Settings) {
-    check_for_updates(app, prerelease, settings, false);
-    save_app_data_offline(&app);
+pub(crate) fn parse_json<T: DeserializeOwned>(response: &str) -> Result<T, GithubError> {
+    serde_json::from_str(response).map_err(|e| GithubError::InvalidResponse(e.to_string()))
+}
+
+// None when the file can't be read or no longer parses, Ex: after a crash mid-write
+fn read_app_data(path: &Path) -> Option<App> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("Failed to open {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(app) => Some(app),
+        Err(e) => {
+            println!("Ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
 }
 
 pub fn save_app_data_offline(app: &App) {
@@ -236,49 +396,162 @@ pub fn save_app_data_offline(app: &App) {
     serde_json::to_writer_pretty(writer, &app).unwrap();
 }
 
-pub fn check_for_all_updates(apps: &mut Vec<App>, prerelease: bool, settings: &mut Settings) {
-    for app in apps.iter_mut() {
-        check_for_updates(app, prerelease, settings, true);
+// The parts of an app an update check reads, so checks can run away from the UI thread
+#[derive(Debug, Clone)]
+pub struct UpdateTarget {
+    pub name: String,
+    pub github_repo: String,
+    pub source: SourceConfig,
+    pub archived: bool,
+    pub pinned: bool,
+    pub version: String,
+    pub update_policy: UpdatePolicy,
+}
+
+impl UpdateTarget {
+    pub fn of(app: &App) -> Self {
+        UpdateTarget {
+            name: app.name.clone(),
+            github_repo: app.github_repo.clone(),
+            source: app.source.clone(),
+            archived: app.github_data.archived,
+            pinned: app.pinned,
+            version: app.version.clone(),
+            update_policy: app.update_policy.clone(),
+        }
+    }
+
+    fn allows(&self, release: &ReleaseData) -> bool {
+        self.update_policy.allows(&self.version, &release.tag_name)
     }
 }
 
-// Override check avoids setting a new time
-pub fn check_for_updates(app: &mut App, prerelease: bool, settings: &mut Settings, override_check: bool) {
-    if !app.github_data.archived && should_check_github(settings) {
-        println!("CHECKING FOR UPDATES");
-        let current_ver = &mut app.version;
-        let latest_data = {
-            let data = get_latest_version_data(&app.github_repo, !prerelease, true);
-            if let Some(release_data) = data.1 {
-                (data.0, release_data)
-            } else {
-                return; // No releases available
+#[derive(Debug, Default)]
+pub struct UpdateCheck {
+    pub releases: Vec<(String, ReleaseData)>, // The newest release on the channel the app's policy allows, by app name
+    pub error: Option<GithubError>, // Apps after the one that failed weren't checked
+}
+
+// Only fetches; apply_latest_release decides on the UI thread whether each release is an update
+pub fn check_for_all_updates(targets: &[UpdateTarget], prerelease: bool, settings: &mut Settings) -> UpdateCheck {
+    let mut check = UpdateCheck::default();
+    let result = if uses_batched_checks(settings) {
+        check_for_all_updates_batched(targets, prerelease, settings, GITHUB_GRAPHQL_URL, &mut check.releases)
+    } else {
+        targets.iter().try_for_each(|target| {
+            if let Some(release_data) = fetch_latest_release(target, prerelease, settings, true)? {
+                check.releases.push((target.name.clone(), release_data));
+            }
+            Ok(())
+        })
+    };
+    check.error = result.err();
+    // Saved once the UI merges the settings back
+    if check.error.is_none() {
+        settings.last_github_check = Some(Utc::now());
+    }
+    check
+}
+
+// One GraphQL request covers every Github app instead of two REST requests each
+pub fn check_for_all_updates_batched(targets: &[UpdateTarget], prerelease: bool, settings: &mut Settings, endpoint: &str, releases: &mut Vec<(String, ReleaseData)>) -> Result<(), GithubError> {
+    let repos: Vec<String> = targets.iter()
+        .filter(|target| !target.archived && !target.pinned && target.source.is_github())
+        .map(|target| target.github_repo.clone())
+        .collect();
+    println!("CHECKING FOR UPDATES (batched)");
+    let mut latest_data = fetch_repositories(endpoint, &settings.github_token, &mut settings.rate_limit, &repos)?;
+    for target in targets {
+        let release_data = if target.pinned {
+            None
+        } else if !target.source.is_github() {
+            fetch_latest_release(target, prerelease, settings, true)?
+        } else if let Some((_, release_data)) = latest_data.remove(&target.github_repo) {
+            match select_release_where(&release_data, prerelease, |release| target.allows(release)) {
+                Some(release) => Some(release),
+                // Only the newest releases come back in the batch, so page through the rest over REST
+                None => fetch_latest_release(target, prerelease, settings, true)?,
             }
+        } else {
+            None
         };
-        let latest_ver = parse_semver(&latest_data.1.tag_name);
-        println!("Current Ver: {}, New Ver: {}", current_ver, latest_ver.to_string());
-        if is_outdated(parse_semver(current_ver), latest_ver) {
-            app.has_update = true;
-            let installation_data = get_installation_data(&app);
-            let download_url = locate_asset(&latest_data.1, &installation_data);
-            app.update_download_url = Some(download_url);
-            app.release_data = latest_data.1;
-            save_app_data_offline(&app);
-        }
-        if !override_check {
-            set_checked_for_update(settings);
+        if let Some(release_data) = release_data {
+            releases.push((target.name.clone(), release_data));
         }
     }
+    Ok(())
+}
+
+fn uses_batched_checks(settings: &Settings) -> bool {
+    settings.batch_update_checks && !settings.github_token.trim().is_empty()
+}
+
+// How many Github requests checking every app will take
+pub fn update_check_cost(settings: &Settings, apps: &[App]) -> usize {
+    let github_apps = apps.iter().filter(|app| app.source.is_github()).count();
+    if uses_batched_checks(settings) {
+        github_apps.min(1)
+    } else {
+        github_apps * REQUESTS_PER_APP
+    }
+}
+
+// Override check avoids setting a new time
+pub fn check_for_updates(app: &mut App, prerelease: bool, settings: &mut Settings, override_check: bool) -> Result<(), GithubError> {
+    if let Some(release_data) = fetch_latest_release(&UpdateTarget::of(app), prerelease, settings, override_check)? {
+        apply_latest_release(app, release_data);
+    }
+    Ok(())
+}
+
+fn fetch_latest_release(target: &UpdateTarget, prerelease: bool, settings: &mut Settings, override_check: bool) -> Result<Option<ReleaseData>, GithubError> {
+    // Pinned apps stay on the version they were frozen at
+    if target.archived || target.pinned || !should_check_source(settings, &target.source) {
+        return Ok(None);
+    }
+    println!("CHECKING FOR UPDATES");
+    let allows = |release: &ReleaseData| target.allows(release);
+    let query = ReleaseQuery { prerelease, max_pages: settings.release_page_limit, list_all: false, allows: &allows };
+    let release_data = match get_latest_version_data(&target.source, &target.github_repo, &query, &settings.github_token, &mut settings.rate_limit)? {
+        (_, Some(release_data)) => release_data,
+        _ => return Ok(None), // No releases available
+    };
+    if !override_check {
+        set_checked_for_update(settings);
+    }
+    Ok(Some(release_data))
+}
+
+pub fn apply_latest_release(app: &mut App, release_data: ReleaseData) {
+    if app.pinned || !app.update_policy.allows(&app.version, &release_data.tag_name) {
+        return;
+    }
+    let current_ver = &app.version;
+    let latest_ver = parse_semver(&release_data.tag_name);
+    println!("Current Ver: {}, New Ver: {}", current_ver, latest_ver);
+    if is_outdated(parse_semver(current_ver), latest_ver) {
+        let download_url = match locate_asset(&release_data, &app.installation_data) {
+            Ok(download_url) => download_url,
+            Err(error) => {
+                println!("Can't update {} to {}: {}", app.name, release_data.tag_name, error);
+                return;
+            }
+        };
+        app.has_update = true;
+        app.update_download_url = Some(download_url);
+        app.release_data = release_data;
+        save_app_data_offline(app);
+    }
 }
 
 // Attempts to make it more readable
-pub fn clean_github_tag(tag: &String) -> String {
+pub fn clean_github_tag(tag: &str) -> String {
     // Remove everything before the first number
     let first_number_index = tag.chars().position(|c| c.is_numeric()).unwrap_or(tag.len());
     tag[first_number_index..].to_string()
 }
 
-pub fn parse_semver(version_str: &String) -> Version {
+pub fn parse_semver(version_str: &str) -> Version {
     let clean_str = clean_github_tag(version_str);
     let version = Version::parse(&clean_str);
     if let Ok(semver) = version {
@@ -326,243 +599,344 @@ fn set_checked_for_update(settings: &mut Settings) {
 
 // File downloading
 // Extension includes the period
-pub fn download_application(app: &App, progress: &Arc<AtomicI8>, sender: &Arc<Sender<(AppEvents, Option<String>)>>) {
-    let installation_data = get_installation_data(&app);
+pub fn download_application(app: &App, settings: &Settings, progress: &Progress, sender: &Arc<Sender<(AppEvents, Option<String>)>>, queue: &DownloadQueue) {
+    let installation_data = app.installation_data.resolve();
     let release_data = app.release_data.clone();
     let name = app.name.clone();
-    let progress_clone = Arc::clone(&progress);
+    let token = app.source.token(&settings.github_token).to_string();
+    let limits = settings.extraction_limits;
+    let policy = app.signature_policy;
+    let control = app.download_control.clone();
     let sender_clone = Arc::clone(sender);
-    thread::spawn(move || {
-        let application_path = Path::new("openlightsmanager/apps/");
-        if !application_path.exists() {
-            fs::create_dir_all(application_path).unwrap();
+    let target = InstallTarget::Active(None);
+    queue.enqueue(&app.name, &app.display_name, JobKind::Install, control, progress.clone(), Arc::clone(sender), Box::new(move |control, progress| {
+        if let Err(e) = fs::create_dir_all(APPS_DIR) {
+            return install_failed(&sender_clone, progress, ArchiveError::Io(e).to_string());
         }
-        for asset in &release_data.assets {
-            let filename = asset.browser_download_url.split('/').last().unwrap_or("unknown");
-            println!("Examining {}", filename);
-            let parts: Vec<&str> = filename.split('.').collect();
-            let asset_extension = parts.last().unwrap_or(&"").to_string();
-
-            if let Some(extension_comparing) = &installation_data.extension {
-                if asset_extension.ne(extension_comparing) {
-                    println!("Bad: extension mismatch; Provided {}, Expected {}", asset_extension, extension_comparing);
-                    continue;
-                }
-            }
-
-            if let Some(key) = &installation_data.key_word {
-                if !filename.contains(key.as_str()) {
-                    println!("Bad: key word mismatch");
-                    continue;
-                }
-            }
-
-            println!("Success");
-            let path_str = download(&asset_extension, filename, &name, &progress_clone, &asset.browser_download_url, &installation_data);
+        let asset = match select_asset(&release_data.assets, &installation_data.asset_rules()) {
+            Ok(asset) => asset,
+            Err(error) => return install_failed(&sender_clone, progress, error.to_string()),
+        };
+        install_asset(asset, &release_data, &name, &token, &installation_data, limits, policy, target, progress, control, &sender_clone)
+    }));
+}
 
-            extract(&asset_extension, &sender_clone, &progress_clone, &installation_data, &name, &path_str);
+// Installs a specific release next to the active version; activate_version switches to it
+pub fn install_version(app: &App, release_data: ReleaseData, settings: &Settings, progress: &Progress, sender: &Arc<Sender<(AppEvents, Option<String>)>>, queue: &DownloadQueue) {
+    let installation_data = app.installation_data.resolve();
+    let name = app.name.clone();
+    let token = app.source.token(&settings.github_token).to_string();
+    let limits = settings.extraction_limits;
+    let policy = app.signature_policy;
+    let control = app.download_control.clone();
+    let sender_clone = Arc::clone(sender);
+    queue.enqueue(&app.name, &app.display_name, JobKind::Version, control, progress.clone(), Arc::clone(sender), Box::new(move |control, progress| {
+        let asset = match select_asset(&release_data.assets, &installation_data.asset_rules()) {
+            Ok(asset) => asset,
+            Err(error) => return install_failed(&sender_clone, progress, error.to_string()),
+        };
+        install_asset(asset, &release_data, &name, &token, &installation_data, limits, policy, InstallTarget::SideBySide, progress, control, &sender_clone)
+    }));
+}
 
-            finalize_download(&installation_data, &sender_clone, filename, &progress_clone);
-            return;
-        }
-        println!("Failed to install");
-        send_event(&sender_clone, AppEvents::Failed, None); // TODO Send failure message
-        progress_clone.store(0, Ordering::Relaxed);
-    });
+// Where a verified install ends up
+#[derive(Debug, Clone)]
+pub enum InstallTarget {
+    Active(Option<String>), // Replaces the active version, which is kept if the app was installed
+    SideBySide, // Kept next to the active version without switching to it
 }
 
-fn download(asset_extension: &String, filename: &str, name: &String, progress_clone: &Arc<AtomicI8>, download_url: &String, installation_data: &InstallationData) -> String {
-    let path_str;
-    if installation_data.is_manager {
-        path_str = format!("{}/{}", current_dir().unwrap().to_string_lossy(), format!("NEW-{}", filename));
-    } else {
-        path_str = if is_archive(&asset_extension) {
-            format!("openlightsmanager/apps/{}", filename)
-        } else {
-            let parent_str =   format!("openlightsmanager/apps/{}/", name);
-            let parent_path = Path::new(&parent_str);
-            if !parent_path.exists() {
-                fs::create_dir_all(parent_path).unwrap();
-            }
-            format!("{}{}", parent_str, filename)
-        };
+// Downloads, verifies and unpacks one asset; shared by installs and updates
+#[allow(clippy::too_many_arguments)]
+fn install_asset(asset: &AssetData, release_data: &ReleaseData, name: &str, token: &str, installation_data: &InstallationData, limits: ExtractionLimits, policy: SignaturePolicy, target: InstallTarget, progress: &Progress, control: &DownloadControl, sender: &Sender<(AppEvents, Option<String>)>) -> JobStatus {
+    let filename = asset_filename(asset);
+    println!("Installing {}", filename);
+
+    // Refused up front, since the download could never be verified
+    if installation_data.is_manager && installation_data.public_keys.is_empty() {
+        return install_failed(sender, progress, SignatureError::NoTrustedKey.to_string());
     }
 
-    let rt = Runtime::new().unwrap();
-    rt.block_on(get_file(download_url, path_str.clone(), &progress_clone));
-    path_str
-}
+    // Looked up first so a missing checksum server doesn't waste a large download
+    let expected = match block_on(expected_sha256(release_data, asset, &installation_data.sha256, token)) {
+        Ok(expected) => expected,
+        Err(error) => {
+            return install_failed(sender, progress, error.to_string());
+        }
+    };
 
-fn extract(asset_extension: &String, sender: &Sender<(AppEvents, Option<String>)>, progress_clone: &Arc<AtomicI8>, installation_data: &InstallationData, name: &String, path_str: &String) {
-    if is_archive(&asset_extension) {
-        send_event(&sender, AppEvents::Extracting, None);
-        progress_clone.store(0, Ordering::Relaxed);
+    let (path_str, sha256) = match download(filename, name, progress, asset, token, installation_data, control) {
+        Ok(downloaded) => downloaded,
+        Err(GithubError::Cancelled) => {
+            return download_cancelled(sender, progress, name, None);
+        }
+        Err(error) => {
+            return download_failed(sender, progress, error);
+        }
+    };
 
-        let extracted_path_str = if installation_data.has_extra_folder {
-            String::from("openlightsmanager/apps/")
-        } else {
-            format!("openlightsmanager/apps/{}/", name)
-        };
-        let extracted_path = Path::new(&extracted_path_str);
-        if !extracted_path.exists() {
-            fs::create_dir(extracted_path).unwrap();
+    progress.start_phase(Phase::Verifying, 0);
+    match check_sha256(expected, sha256) {
+        Ok(ChecksumStatus::Verified(sha256)) => send_event(sender, AppEvents::Verified, Some(sha256)),
+        Ok(_) => {
+            println!("No checksum published for {}", filename);
+            send_event(sender, AppEvents::Verified, None);
         }
-        let path = Path::new(&path_str);
-        let file = File::open(path).unwrap();
-        let mut archive = ZipArchive::new(file).unwrap();
-
-        let total_files = archive.len();
-        for i in 0..total_files {
-            let mut file = archive.by_index(i).unwrap();
-            #[allow(deprecated)]
-            let file_name = file.sanitized_name();
-            let extracted_file_path = extracted_path.join(file_name);
-
-            if file.is_dir() {
-                fs::create_dir_all(&extracted_file_path).unwrap();
-            } else {
-                let parent = extracted_file_path.parent().unwrap();
-                if !parent.exists() {
-                    fs::create_dir_all(parent).unwrap();
-                }
-                let mut extracted_file = File::create(&extracted_file_path).unwrap();
-                io::copy(&mut file, &mut extracted_file).unwrap();
+        Err(error) => {
+            if let Err(e) = fs::remove_file(&path_str) {
+                println!("Failed to remove {}: {}", path_str, e);
             }
+            return install_failed(sender, progress, error.to_string());
+        }
+    }
 
-            let progress = ((i as f32 + 1.) * 100.) / total_files as f32;
-            let progress_rounded = progress.ceil() as i8;
-            progress_clone.store(progress_rounded, Ordering::Relaxed);
+    // The manager replaces its own executable, so nothing unsigned gets through; without a pinned key it refuses to update
+    let policy = if installation_data.is_manager { SignaturePolicy::Require } else { policy };
+    match block_on(verify_download(Path::new(&path_str), release_data, asset, &installation_data.public_keys, policy, token)) {
+        Ok(true) => println!("Verified the signature of {}", filename),
+        Ok(false) => {}
+        Err(error) => {
+            if let Err(e) = fs::remove_file(&path_str) {
+                println!("Failed to remove {}: {}", path_str, e);
+            }
+            return install_failed(sender, progress, error.to_string());
         }
+    }
 
-        // App-specific tasks
+    // Last chance to back out; the installed version hasn't been touched yet
+    if control.is_cancelled() {
+        return download_cancelled(sender, progress, name, Some(&path_str));
+    }
+    if installation_data.is_manager {
+        return finalize_download(installation_data, sender, filename, progress);
+    }
 
-        if installation_data.has_extra_folder {
-            for entry in extracted_path.read_dir().expect("Failed to read directory") {
-                if let Ok(entry) = entry {
-                    let entry_path = entry.path();
-                    if entry_path.is_dir() && entry_path.file_name().unwrap().to_string_lossy().contains(&installation_data.extra_folder_key_word.clone().unwrap().as_str()) {
-                        let new_entry_path_str = format!("openlightsmanager/apps/{}/", name);
-                        let new_entry_path = Path::new(&new_entry_path_str);
-                        fs::rename(entry_path, new_entry_path).unwrap();
-                    }
+    // Built up on the side, then swapped in whole so a failure never leaves a half-installed app
+    let dirs = AppDirs::new(name);
+    let version = &release_data.tag_name;
+    let staged = stage(filename, sender, progress, installation_data, &dirs, &path_str, limits)
+        .and_then(|_| dirs.verify_staged(&installation_data.app_path).map_err(ArchiveError::Io))
+        .and_then(|_| match &target {
+            InstallTarget::Active(active_version) => dirs.swap_in(active_version.as_deref(), version).map(|kept| {
+                if kept {
+                    println!("Kept the previous version of {} for rollback", name);
                 }
-            }
-        }
+            }),
+            InstallTarget::SideBySide => dirs.store_staged(version),
+        }.map_err(ArchiveError::Io));
+    dirs.discard_staging();
+    if let Err(error) = staged {
+        return install_failed(sender, progress, error.to_string());
+    }
 
-        fs::remove_file(path).unwrap();
+    if let InstallTarget::SideBySide = target {
+        send_event(sender, AppEvents::VersionInstalled, Some(version.clone()));
+        progress.finish();
+        return JobStatus::Finished;
     }
+    finalize_download(installation_data, sender, filename, progress)
 }
 
-fn finalize_download(installation_data: &InstallationData, sender: &Sender<(AppEvents, Option<String>)>, filename: &str, progress_clone: &Arc<AtomicI8>) {
-    // Is Java
-    if installation_data.is_library && filename.contains("jdk") {
-        send_event(&sender, AppEvents::JavaInstalled, Some(installation_data.app_path.clone()));
-    } else if installation_data.is_manager {
-        send_event(&sender, AppEvents::ManagerInstalled, None);
+// Returns where the file went and its SHA-256
+#[allow(clippy::too_many_arguments)]
+fn download(filename: &str, name: &str, progress: &Progress, asset: &AssetData, token: &str, installation_data: &InstallationData, control: &DownloadControl) -> Result<(String, String), GithubError> {
+    let path_str = if installation_data.is_manager {
+        let dir = current_dir().map_err(|e| GithubError::Io(e.to_string()))?;
+        format!("{}/NEW-{}", dir.to_string_lossy(), filename)
     } else {
-        send_event(&sender, AppEvents::AppInstalled, None);
-    }
-    progress_clone.store(0, Ordering::Relaxed);
-    println!("Finished Installing!");
-}
+        let download_path = AppDirs::new(name).download;
+        fs::create_dir_all(&download_path).map_err(|e| GithubError::Io(e.to_string()))?;
+        download_path.join(filename).to_string_lossy().to_string()
+    };
 
-fn locate_asset(release_data: &ReleaseData, installation_data: &InstallationData) -> String {
-    for asset in &release_data.assets {
-        let filename = asset.browser_download_url.split('/').last().unwrap_or("unknown");
-        let parts: Vec<&str> = filename.split('.').collect();
-        let asset_extension = parts.last().unwrap_or(&"").to_string();
+    // Unknown sizes are listed as 0
+    let expected_size = asset.size.max(0) as u64;
+    let sha256 = block_on(get_file(&asset.browser_download_url, token, &path_str, expected_size, progress, control))?;
+    Ok((path_str, sha256))
+}
 
-        if let Some(extension_comparing) = &installation_data.extension {
-            if asset_extension.ne(extension_comparing) {
-                continue;
-            }
-        }
+fn install_failed(sender: &Sender<(AppEvents, Option<String>)>, progress: &Progress, reason: String) -> JobStatus {
+    println!("Failed to install: {}", reason);
+    send_event(sender, AppEvents::Failed, Some(reason.clone()));
+    progress.finish();
+    JobStatus::Failed(reason)
+}
 
-        if let Some(key) = &installation_data.key_word {
-            if !filename.contains(key.as_str()) {
-                continue;
-            }
+// Leaves things as they were before the download started
+fn download_cancelled(sender: &Sender<(AppEvents, Option<String>)>, progress: &Progress, name: &str, downloaded: Option<&str>) -> JobStatus {
+    println!("Cancelled installing {}", name);
+    if let Some(path_str) = downloaded {
+        if let Err(e) = fs::remove_file(path_str) {
+            println!("Failed to remove {}: {}", path_str, e);
         }
-
-        return asset.browser_download_url.clone();
     }
-    String::new()
+    AppDirs::new(name).discard_staging();
+    send_event(sender, AppEvents::Cancelled, None);
+    progress.finish();
+    JobStatus::Cancelled
+}
+
+fn download_failed(sender: &Sender<(AppEvents, Option<String>)>, progress: &Progress, error: GithubError) -> JobStatus {
+    println!("Failed to download: {}", error);
+    let event = match error {
+        GithubError::Unauthorized(_) => AppEvents::BadToken,
+        _ => AppEvents::Failed,
+    };
+    send_event(sender, event, None);
+    progress.finish();
+    JobStatus::Failed(error.to_string())
 }
 
-async fn get_file(url: &String, path: String, progress: &Arc<AtomicI8>) {
-    let response = reqwest::get(url).await.unwrap();
-    let content_length = response.content_length().unwrap_or(0);
+// Unpacks or copies the download into the staging folder
+fn stage(filename: &str, sender: &Sender<(AppEvents, Option<String>)>, progress: &Progress, installation_data: &InstallationData, dirs: &AppDirs, path_str: &str, limits: ExtractionLimits) -> Result<(), ArchiveError> {
+    let path = Path::new(&path_str);
+    if !is_archive_name(filename) {
+        dirs.prepare_staging(true)?;
+        return Ok(fs::rename(path, dirs.staging.join(filename))?);
+    }
 
-    let mut total_bytes_read = 0;
-    let mut file = File::create(&path).unwrap();
+    send_event(sender, AppEvents::Extracting, None);
+    progress.finish();
+    dirs.prepare_staging(false)?;
+    // Archives with an extra top folder are unpacked on the side, then that folder becomes the staged app
+    let result = if installation_data.has_extra_folder {
+        extract_archive(path, &dirs.extracting, limits, progress)
+            .and_then(|_| Ok(move_extra_folder(&dirs.extracting, installation_data, &dirs.staging)?))
+    } else {
+        extract_archive(path, &dirs.staging, limits, progress)
+    };
+    if let Err(e) = fs::remove_file(path) {
+        println!("Failed to remove {}: {}", path_str, e);
+    }
+    result
+}
 
-    let mut stream = response.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.unwrap();
-        total_bytes_read += chunk.len() as u64;
-        file.write_all(&chunk).unwrap();
+fn move_extra_folder(extracted_path: &Path, installation_data: &InstallationData, app_path: &Path) -> io::Result<()> {
+    let key_word = installation_data.extra_folder_key_word.clone().unwrap_or_default();
+    for entry in extracted_path.read_dir()?.flatten() {
+        let entry_path = entry.path();
+        if entry_path.is_dir() && entry.file_name().to_string_lossy().contains(key_word.as_str()) {
+            if app_path.exists() {
+                fs::remove_dir_all(app_path)?;
+            }
+            return fs::rename(entry_path, app_path);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("No folder containing \"{}\" in the archive", key_word)))
+}
 
-        let progress_percentage = ((total_bytes_read * 100) as f64 / content_length as f64).round() as i8;
-        progress.store(progress_percentage, Ordering::Relaxed);
+fn finalize_download(installation_data: &InstallationData, sender: &Sender<(AppEvents, Option<String>)>, filename: &str, progress: &Progress) -> JobStatus {
+    // Is Java
+    if installation_data.is_library && filename.contains("jdk") {
+        send_event(sender, AppEvents::JavaInstalled, Some(installation_data.app_path.clone()));
+    } else if installation_data.is_manager {
+        send_event(sender, AppEvents::ManagerInstalled, None);
+    } else {
+        send_event(sender, AppEvents::AppInstalled, None);
     }
+    progress.finish();
+    println!("Finished Installing!");
+    JobStatus::Finished
+}
+
+fn locate_asset(release_data: &ReleaseData, installation_data: &InstallationData) -> Result<String, AssetMatchError> {
+    let asset = select_asset(&release_data.assets, &installation_data.resolve().asset_rules())?;
+    Ok(asset.browser_download_url.clone())
 }
 
-pub fn update(app: &App, progress: &Arc<AtomicI8>, sender: &Arc<Sender<(AppEvents, Option<String>)>>) {
-    let download_url = <Option<String> as Clone>::clone(&app.update_download_url).unwrap();
-    let installation_data = get_installation_data(&app);
-    let filename = download_url.split('/').last().clone().unwrap_or("unknown").to_string();
-    let parts: Vec<&str> = filename.split('.').collect();
-    let asset_extension = parts.last().unwrap_or(&"").to_string();
+pub fn update(app: &App, settings: &Settings, progress: &Progress, sender: &Arc<Sender<(AppEvents, Option<String>)>>, queue: &DownloadQueue) {
+    // Cleared when the update was applied or another version was activated in the meantime
+    let Some(download_url) = app.update_download_url.clone() else {
+        send_event(sender, AppEvents::Failed, Some("No update is available to download".to_string()));
+        return;
+    };
+    let token = app.source.token(&settings.github_token).to_string();
+    let limits = settings.extraction_limits;
+    let policy = app.signature_policy;
+    let installation_data = app.installation_data.resolve();
+    let release_data = app.release_data.clone();
     let name = app.name.clone();
-    let progress_clone = Arc::clone(&progress);
+    let control = app.download_control.clone();
     let sender_clone = Arc::clone(sender);
-    thread::spawn(move || {
-        // Clear old files
-        if is_archive(&asset_extension) {
-            // None of my apps would come in archive form, so it's safe to delete the entire thing
-            let path_str = format!("openlightsmanager/apps/{}", &name);
-            let path = Path::new(&path_str);
-
-            if path.exists() {
-                fs::remove_dir_all(path).unwrap();
-            }
-        } else {
-            let path_str = format!("openlightsmanager/apps/{}/{}", &name, &filename);
-            let path = Path::new(&path_str);
+    let target = InstallTarget::Active(Some(app.version.clone()));
+    queue.enqueue(&app.name, &app.display_name, JobKind::Update, control, progress.clone(), Arc::clone(sender), Box::new(move |control, progress| {
+        // The previous version stays in place until the new one is downloaded and verified
+        // The release normally lists the asset, which is needed to find its checksum
+        let asset = release_data.assets.iter()
+            .find(|asset| asset.browser_download_url == download_url)
+            .cloned()
+            .unwrap_or(AssetData {
+                size: 0,
+                browser_download_url: download_url,
+                digest: None,
+            });
+        install_asset(&asset, &release_data, &name, &token, &installation_data, limits, policy, target, progress, control, &sender_clone)
+    }));
+}
 
-            if path.exists() {
-                fs::remove_file(path).unwrap();
-            }
-        }
+// Switches to a version installed side by side; the one it replaces is kept and becomes the rollback
+pub fn activate_version(app: &mut App, version: &str) -> io::Result<()> {
+    let active_version = app.installed.then(|| app.version.clone());
+    AppDirs::new(&app.name).activate(active_version.as_deref(), version)?;
+    app.previous_version = active_version;
+    app.version = version.to_string();
+    app.installed = true;
+    // The checksum was for the version that was just put aside
+    app.checksum = ChecksumStatus::Unknown;
+    // A newer release is offered as an update again
+    app.has_update = false;
+    app.update_download_url = None;
+    let release_data = app.release_data.clone();
+    apply_latest_release(app, release_data);
+    save_app_data_offline(app);
+    Ok(())
+}
 
-        // Download new version
-        let path_str = download(&asset_extension, &filename, &name, &progress_clone, &download_url, &installation_data);
+// Swaps back to the version kept by the last update or version switch
+pub fn roll_back(app: &mut App) -> io::Result<()> {
+    match app.previous_version.clone() {
+        Some(previous_version) => activate_version(app, &previous_version),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "No previous version was kept")),
+    }
+}
 
-        extract(&asset_extension, &sender_clone, &progress_clone, &installation_data, &name, &path_str);
+// Pinned apps are skipped by update checks until they are unpinned
+pub fn set_pinned(app: &mut App, pinned: bool) {
+    app.pinned = pinned;
+    if pinned {
+        app.has_update = false;
+        app.update_download_url = None;
+    } else {
+        let release_data = app.release_data.clone();
+        apply_latest_release(app, release_data);
+    }
+    save_app_data_offline(app);
+}
 
-        finalize_download(&installation_data, &sender_clone, &filename, &progress_clone);
-    });
+// Re-decides whether the last release found is an update; a looser policy needs another check to find newer ones
+pub fn set_update_policy(app: &mut App, update_policy: UpdatePolicy) {
+    app.update_policy = update_policy;
+    app.has_update = false;
+    app.update_download_url = None;
+    let release_data = app.release_data.clone();
+    apply_latest_release(app, release_data);
+    save_app_data_offline(app);
 }
 
 pub fn update_app_data(app: &mut App) {
+    // Updates keep the version they replaced for rollback
+    app.previous_version = if AppDirs::new(&app.name).has_version(&app.version) {
+        Some(app.version.clone())
+    } else {
+        None
+    };
     app.version = app.release_data.tag_name.clone();
     app.update_download_url = None;
     app.has_update = false;
-    save_app_data_offline(&app);
-}
-
-fn is_archive(extension: &String) -> bool {
-    match extension.as_str() {
-        "zip" => true,
-        "rar" => true,
-        "7z" => true,
-        "tar" => true,
-        "gz" => true,
-        _ => false,
-    }
+    save_app_data_offline(app);
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct InstallationData {
     pub launchable: bool,
     launch_cmd: Option<String>, // Use {jvm} for java path; Use {app} for app path to executable
@@ -572,43 +946,96 @@ pub struct InstallationData {
     extra_folder_key_word: Option<String>,
     extension: Option<String>,
     key_word: Option<String>,
+    #[serde(default)]
+    assets: AssetRules, // Combined with extension and key_word
     pub app_path: String,
+    #[serde(default)]
+    platforms: Vec<PlatformVariant>, // Checked in order; the first one matching this machine wins
+    #[serde(default)]
+    pub public_keys: Vec<String>, // Minisign keys trusted to sign releases, Ex: "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"
+    #[serde(default)]
+    pub sha256: HashMap<String, String>, // Digests vouched for by the catalog, by asset file name; trusted over the release's own
 }
 
-impl Default for InstallationData {
-    fn default() -> Self {
-        InstallationData {
-            launchable: false,
-            launch_cmd: None,
-            is_library: false,
-            is_manager: false,
-            has_extra_folder: false,
-            extra_folder_key_word: None,
-            extension: None,
-            key_word: None,
-            app_path: String::new(),
-        }
+// Overrides for a single OS and/or architecture, Ex: { "os": "linux", "arch": "aarch64", "key_word": "linux-aarch64" }
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct PlatformVariant {
+    os: Option<String>, // As in std::env::consts::OS: windows, linux, macos
+    arch: Option<String>, // As in std::env::consts::ARCH: x86_64, aarch64
+    launch_cmd: Option<String>,
+    has_extra_folder: Option<bool>,
+    extra_folder_key_word: Option<String>,
+    extension: Option<String>,
+    key_word: Option<String>,
+    assets: Option<AssetRules>,
+    app_path: Option<String>,
+}
+
+impl PlatformVariant {
+    fn matches(&self, os: &str, arch: &str) -> bool {
+        self.os.as_deref().is_none_or(|variant_os| variant_os == os)
+            && self.arch.as_deref().is_none_or(|variant_arch| variant_arch == arch)
     }
 }
 
-pub fn get_installation_data(app: &App) -> InstallationData {
-    let path_str = format!("assets/{}.json", app.name);
-    let path = Path::new(&path_str);
-    let file = File::open(path).unwrap();
-    let mut reader = BufReader::new(file);
-    let result: Result<InstallationData, serde_json::Error> = serde_json::from_reader(&mut reader);
-    result.unwrap()
+impl InstallationData {
+    // The rules to use on this machine
+    pub fn resolve(&self) -> InstallationData {
+        self.resolve_for(consts::OS, consts::ARCH)
+    }
+
+    // Descriptors without variants are assumed to work everywhere
+    pub fn supports_current_platform(&self) -> bool {
+        self.platforms.is_empty() || self.variant(consts::OS, consts::ARCH).is_some()
+    }
+
+    // The descriptor's own rules plus the extension and key word filters
+    pub fn asset_rules(&self) -> AssetRules {
+        let mut rules = self.assets.clone();
+        if let Some(extension) = &self.extension {
+            if extension.is_empty() {
+                rules.include.push(String::from(r"^[^.]*$"));
+            } else {
+                rules.include.push(format!(r"\.{}$", regex::escape(extension)));
+            }
+        }
+        if let Some(key_word) = &self.key_word {
+            rules.include.push(regex::escape(key_word));
+        }
+        rules
+    }
+
+    fn variant(&self, os: &str, arch: &str) -> Option<&PlatformVariant> {
+        self.platforms.iter().find(|variant| variant.matches(os, arch))
+    }
+
+    fn resolve_for(&self, os: &str, arch: &str) -> InstallationData {
+        let mut resolved = self.clone();
+        resolved.platforms = Vec::new();
+        if let Some(variant) = self.variant(os, arch) {
+            let variant = variant.clone();
+            resolved.launch_cmd = variant.launch_cmd.or(resolved.launch_cmd);
+            resolved.has_extra_folder = variant.has_extra_folder.unwrap_or(resolved.has_extra_folder);
+            resolved.extra_folder_key_word = variant.extra_folder_key_word.or(resolved.extra_folder_key_word);
+            resolved.extension = variant.extension.or(resolved.extension);
+            resolved.key_word = variant.key_word.or(resolved.key_word);
+            resolved.assets = variant.assets.unwrap_or(resolved.assets);
+            resolved.app_path = variant.app_path.unwrap_or(resolved.app_path);
+        }
+        resolved
+    }
 }
 
-pub fn launch_application(app: &mut App, jvm_path_og: &String) -> Notification {
+pub fn launch_application(app: &mut App, jvm_path_og: &str) -> Notification {
     app.event = AppEvents::Running;
-    let installation_data = get_installation_data(&app);
+    let installation_data = app.installation_data.resolve();
     let app_name = app.name.clone();
-    let jvm_path = jvm_path_og.clone();
+    let jvm_path = jvm_path_og.to_string();
     let id_clone = Arc::clone(&app.process);
+    let sender = Arc::clone(&app.thread_communication.event_sender);
     let cmd = installation_data.launch_cmd.unwrap_or(format!("{}{}", app_name, installation_data.app_path));
     if cmd.contains("{jvm}") && jvm_path.is_empty() {
-        return launched_application_missing_java(&app_name);
+        return launched_application_missing_java(&app.display_name);
     }
 
     thread::spawn(move || {
@@ -621,15 +1048,21 @@ pub fn launch_application(app: &mut App, jvm_path_og: &String) -> Notification {
         let dir = Path::new(app_path.as_str());
         let filled_in = cmd.replace("{jvm} ", "").replace("{app}", installation_data.app_path.replace("/", "").as_str());
         let split: Vec<&str> = filled_in.split_whitespace().collect();
-        id_clone.store(Command::new(main_argument_path)
-                           .current_dir(dir)
-                           .args(split)
-                           .spawn()
-                           .expect("Failed to run application")
-                           .id(), Ordering::Relaxed);
+        let mut child = Command::new(main_argument_path)
+            .current_dir(dir)
+            .args(split)
+            .spawn()
+            .expect("Failed to run application");
+        id_clone.store(child.id(), Ordering::Relaxed);
+        // Updates wait until the app has closed
+        if let Err(e) = child.wait() {
+            println!("Lost track of {}: {}", app_name, e);
+        }
+        id_clone.store(0, Ordering::Relaxed);
+        send_event(&sender, AppEvents::Exited, None);
     });
 
-    launched_application(&app.name)
+    launched_application(&app.display_name)
 }
 
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -643,8 +1076,214 @@ pub enum AppEvents {
     JavaInstalled,
     ManagerInstalled,
     Running,
+    Exited, // The launched app closed
+    BadToken,
+    Verified, // Carries the SHA-256 when the release published one
+    Cancelled,
+    VersionInstalled, // Carries the tag, installed next to the active version
 }
 
+// The app may be gone by the time a worker finishes, which is fine
 fn send_event(sender: &Sender<(AppEvents, Option<String>)>, event: AppEvents, data: Option<String>) {
-    sender.send((event, data)).unwrap();
-}
\ No newline at end of file
+    let _ = sender.send((event, data));
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::IndexSource;
+    use crate::update_policy::UpdateRange;
+
+    fn release(tag_name: &str, prerelease: bool, draft: bool) -> ReleaseData {
+        ReleaseData {
+            tag_name: tag_name.to_string(),
+            prerelease,
+            draft,
+            id: 0,
+            assets: Vec::new(),
+            ..ReleaseData::default()
+        }
+    }
+
+    fn selected_tag(release_data: &[ReleaseData], prerelease: bool) -> Option<String> {
+        select_release(release_data, prerelease).map(|release| release.tag_name)
+    }
+
+    #[test]
+    fn stable_channel_picks_newest_stable_release() {
+        let release_data = vec![
+            release("v2.0.0-pre.2", true, false),
+            release("v1.2.0", false, false),
+            release("v1.10.0", false, false),
+            release("v1.9.0", false, false),
+        ];
+        assert_eq!(selected_tag(&release_data, false).as_deref(), Some("v1.10.0"));
+    }
+
+    #[test]
+    fn prerelease_channel_picks_newest_across_both() {
+        let release_data = vec![
+            release("v1.1.0", false, false),
+            release("v2.0.0-pre.1", true, false),
+            release("v2.0.0-pre.2", true, false),
+        ];
+        assert_eq!(selected_tag(&release_data, true).as_deref(), Some("v2.0.0-pre.2"));
+
+        // A stable release beats the prereleases leading up to it
+        let release_data = vec![
+            release("v2.0.0-rc.1", true, false),
+            release("v2.0.0", false, false),
+        ];
+        assert_eq!(selected_tag(&release_data, true).as_deref(), Some("v2.0.0"));
+    }
+
+    #[test]
+    fn drafts_are_ignored() {
+        let release_data = vec![
+            release("v3.0.0", false, true),
+            release("v2.1.0-pre.1", true, true),
+            release("v2.0.0", false, false),
+        ];
+        assert_eq!(selected_tag(&release_data, false).as_deref(), Some("v2.0.0"));
+        assert_eq!(selected_tag(&release_data, true).as_deref(), Some("v2.0.0"));
+        assert_eq!(selected_tag(&[release("v1.0.0", false, true)], true), None);
+    }
+
+    #[test]
+    fn semver_prerelease_tags_stay_off_the_stable_channel() {
+        let release_data = vec![
+            release("v1.1.0-beta.1", false, false),
+            release("v1.0.0", false, false),
+        ];
+        assert_eq!(selected_tag(&release_data, false).as_deref(), Some("v1.0.0"));
+        assert_eq!(selected_tag(&[release("v1.1.0-beta.1", true, false)], false), None);
+    }
+
+    #[test]
+    fn platform_variants_override_the_base_rules() {
+        let installation_data: InstallationData = serde_json::from_str(r#"{
+            "launchable": true, "is_library": true, "is_manager": false, "has_extra_folder": true,
+            "extension": "zip", "key_word": "windows-x64", "app_path": "/bin/javaw.exe",
+            "platforms": [
+                { "os": "windows" },
+                { "os": "linux", "arch": "aarch64", "extension": "gz", "key_word": "linux-aarch64", "app_path": "/bin/java" },
+                { "os": "linux", "extension": "gz", "key_word": "linux-x64", "app_path": "/bin/java" }
+            ]
+        }"#).unwrap();
+
+        let windows = installation_data.resolve_for("windows", "x86_64");
+        assert_eq!(windows.key_word.as_deref(), Some("windows-x64"));
+        assert_eq!(windows.app_path, "/bin/javaw.exe");
+
+        let raspberry_pi = installation_data.resolve_for("linux", "aarch64");
+        assert_eq!(raspberry_pi.extension.as_deref(), Some("gz"));
+        assert_eq!(raspberry_pi.key_word.as_deref(), Some("linux-aarch64"));
+        assert_eq!(raspberry_pi.app_path, "/bin/java");
+        assert!(raspberry_pi.has_extra_folder);
+
+        assert_eq!(installation_data.resolve_for("linux", "x86_64").key_word.as_deref(), Some("linux-x64"));
+        assert!(installation_data.variant("macos", "aarch64").is_none());
+    }
+
+    #[test]
+    fn finds_next_page_in_link_header() {
+        let link = r#"<https://api.github.com/repositories/1/releases?page=2>; rel="next", <https://api.github.com/repositories/1/releases?page=5>; rel="last""#;
+        assert_eq!(next_page_url(link).as_deref(), Some("https://api.github.com/repositories/1/releases?page=2"));
+
+        let last_page = r#"<https://api.github.com/repositories/1/releases?page=4>; rel="prev", <https://api.github.com/repositories/1/releases?page=1>; rel="first""#;
+        assert_eq!(next_page_url(last_page), None);
+    }
+
+    #[test]
+    fn only_refusals_of_a_sent_token_blame_it() {
+        let api = reqwest::Url::parse("https://api.github.com/repos/Open-Lights/BeatMaker/releases").unwrap();
+        let cdn = reqwest::Url::parse("https://objects.githubusercontent.com/github-production-release-asset/1").unwrap();
+        let gitea = reqwest::Url::parse("https://gitea.example.com/api/v1/repos/open-lights/beatmaker").unwrap();
+        assert!(sent_github_token(&api, "token"));
+        assert!(!sent_github_token(&api, " "));
+        assert!(!sent_github_token(&cdn, "token"));
+        assert!(!sent_github_token(&gitea, "token"));
+    }
+
+    #[test]
+    fn unparsable_tags_fall_back_to_listing_order() {
+        let release_data = vec![
+            release("nightly-b", false, false),
+            release("nightly-a", false, false),
+        ];
+        assert_eq!(selected_tag(&release_data, false).as_deref(), Some("nightly-b"));
+        assert_eq!(selected_tag(&[], true), None);
+    }
+
+    #[test]
+    fn update_policies_pick_the_newest_allowed_release() {
+        let release_data = vec![
+            release("v2.0.0", false, false),
+            release("v1.5.0", false, false),
+            release("v1.4.3", false, false),
+        ];
+        let mut policy = UpdatePolicy { range: UpdateRange::SameMajor, ..UpdatePolicy::default() };
+        let newest_allowed = |policy: &UpdatePolicy| select_release_where(&release_data, false, |release| policy.allows("v1.4.2", &release.tag_name)).map(|release| release.tag_name);
+        assert_eq!(newest_allowed(&policy).as_deref(), Some("v1.5.0"));
+
+        policy.skip("v1.5.0");
+        assert_eq!(newest_allowed(&policy).as_deref(), Some("v1.4.3"));
+        policy.range = UpdateRange::SameMinor;
+        policy.skip("v1.4.3");
+        assert_eq!(newest_allowed(&policy), None);
+    }
+
+    #[test]
+    fn collects_every_release_an_update_skips() {
+        let release_data = vec![
+            release("v2.1.0", false, false),
+            release("v2.0.0", false, false),
+            release("v2.0.0-beta.1", true, false),
+            release("v1.9.0", false, true),
+            release("v1.8.0", false, false),
+            release("v1.7.0", false, false),
+        ];
+        let tags = |prerelease: bool| -> Vec<String> {
+            releases_between(&release_data, "v1.7.0", "v2.0.0", prerelease).into_iter().map(|release| release.tag_name).collect()
+        };
+        assert_eq!(tags(false), ["v2.0.0", "v1.8.0"]);
+        assert_eq!(tags(true), ["v2.0.0", "v2.0.0-beta.1", "v1.8.0"]);
+        // Tags that aren't semver can't be placed, so only the target's notes are shown
+        assert_eq!(releases_between(&[release("nightly", false, false)], "nightly-old", "nightly", false).len(), 1);
+    }
+
+    #[test]
+    fn pinned_apps_are_never_checked() {
+        let target = UpdateTarget {
+            name: "BeatMaker".to_string(),
+            github_repo: "Open-Lights/BeatMaker".to_string(),
+            // Any request would fail, so an empty result means none was made
+            source: SourceConfig::Index(IndexSource { url: "file:///nonexistent/releases.json".to_string() }),
+            archived: false,
+            pinned: true,
+            version: "v1.0.0".to_string(),
+            update_policy: UpdatePolicy::default(),
+        };
+        let mut settings = Settings::default();
+        let check = check_for_all_updates(std::slice::from_ref(&target), false, &mut settings);
+        assert!(check.releases.is_empty());
+        assert_eq!(check.error, None);
+
+        let unpinned = UpdateTarget { pinned: false, ..target };
+        assert!(check_for_all_updates(&[unpinned], false, &mut settings).error.is_some());
+    }
+
+    #[test]
+    fn file_urls_are_decoded_into_paths() {
+        assert_eq!(local_file_path("file:///srv/Beat%20Maker/releases.json"), Ok(Some(PathBuf::from("/srv/Beat Maker/releases.json"))));
+        assert_eq!(local_file_path("https://example.com/releases.json"), Ok(None));
+    }
+
+    #[test]
+    fn unreadable_app_data_is_skipped() {
+        let path = std::env::temp_dir().join(format!("openlightsmanager-appdata-{}.json", fastrand::u64(..)));
+        fs::write(&path, "{\"installed\": tr").unwrap();
+        assert!(read_app_data(&path).is_none());
+        fs::remove_file(&path).unwrap();
+        assert!(read_app_data(&path).is_none());
+    }
+}
@@ -1,7 +1,24 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod archive;
+pub mod asset_match;
+pub mod catalog;
+pub mod checksum;
+pub mod download;
+pub mod download_queue;
+pub mod graphql;
 pub mod gui;
+pub mod http_cache;
 pub mod io;
+pub mod markdown;
 pub mod notifications;
+pub mod progress;
+pub mod rate_limit;
+pub mod scheduler;
 pub mod settings;
+pub mod signature;
+pub mod source;
+pub mod staging;
+pub mod tasks;
+pub mod update_policy;
 pub use gui::OpenLightsManager;
\ No newline at end of file
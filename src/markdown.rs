@@ -0,0 +1,300 @@
+use egui::{Color32, RichText, Ui};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+// Release notes are drawn a block at a time, each block a wrapped row of styled spans
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, Vec<Span>),
+    Paragraph {
+        indent: usize, // How deeply nested in lists and quotes
+        marker: Option<String>, // Ex: • or 2. for list items
+        spans: Vec<Span>,
+    },
+    Code(String),
+    Rule,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub strong: bool,
+    pub emphasis: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    pub link: Option<String>,
+}
+
+impl Span {
+    fn same_style(&self, other: &Span) -> bool {
+        self.strong == other.strong
+            && self.emphasis == other.emphasis
+            && self.strikethrough == other.strikethrough
+            && self.code == other.code
+            && self.link == other.link
+    }
+}
+
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<Block>,
+    spans: Vec<Span>,
+    heading: Option<u8>,
+    marker: Option<String>,
+    lists: Vec<Option<u64>>, // The next number of each ordered list, None for bullets
+    quotes: usize,
+    code: Option<String>,
+    strong: usize,
+    emphasis: usize,
+    strikethrough: usize,
+    link: Option<String>,
+}
+
+impl BlockBuilder {
+    fn push_text(&mut self, text: &str, code: bool) {
+        let span = Span {
+            text: text.to_string(),
+            strong: self.strong > 0,
+            emphasis: self.emphasis > 0,
+            strikethrough: self.strikethrough > 0,
+            code,
+            link: self.link.clone(),
+        };
+        match self.spans.last_mut() {
+            Some(last) if last.same_style(&span) => last.text.push_str(text),
+            _ => self.spans.push(span),
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.spans.is_empty() {
+            return;
+        }
+        let spans = std::mem::take(&mut self.spans);
+        let block = match self.heading {
+            Some(level) => Block::Heading(level, spans),
+            None => Block::Paragraph {
+                indent: self.lists.len() + self.quotes,
+                marker: self.marker.take(),
+                spans,
+            },
+        };
+        self.blocks.push(block);
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph => self.flush(),
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.heading = Some(level as u8);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.code = Some(String::new());
+            }
+            Tag::List(first) => {
+                self.flush();
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.flush();
+                self.marker = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_string(),
+                });
+            }
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strong => self.strong += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            // Images can't be shown inline, so they become a link to the image
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => self.link = web_link(&dest_url),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quotes = self.quotes.saturating_sub(1);
+            }
+            TagEnd::CodeBlock => {
+                if let Some(code) = self.code.take() {
+                    self.blocks.push(Block::Code(code.trim_end().to_string()));
+                }
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.marker = None;
+            }
+            TagEnd::TableCell => self.push_text("   ", false),
+            TagEnd::Emphasis => self.emphasis = self.emphasis.saturating_sub(1),
+            TagEnd::Strong => self.strong = self.strong.saturating_sub(1),
+            TagEnd::Strikethrough => self.strikethrough = self.strikethrough.saturating_sub(1),
+            TagEnd::Link | TagEnd::Image => self.link = None,
+            _ => {}
+        }
+    }
+}
+
+// Anything else, Ex: file: or javascript:, is shown as plain text rather than handed to the system to open
+fn web_link(url: &str) -> Option<String> {
+    let scheme = url.split_once(':')?.0;
+    if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") {
+        Some(url.to_string())
+    } else {
+        None
+    }
+}
+
+pub fn markdown_blocks(markdown: &str) -> Vec<Block> {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+    let mut builder = BlockBuilder::default();
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(tag) => builder.start(tag),
+            Event::End(tag) => builder.end(tag),
+            Event::Text(text) => match &mut builder.code {
+                Some(code) => code.push_str(&text),
+                None => builder.push_text(&text, false),
+            },
+            Event::Code(text) => builder.push_text(&text, true),
+            Event::SoftBreak => builder.push_text(" ", false),
+            Event::HardBreak => builder.push_text("\n", false),
+            Event::TaskListMarker(done) => builder.push_text(if done { "[x] " } else { "[ ] " }, false),
+            Event::Rule => {
+                builder.flush();
+                builder.blocks.push(Block::Rule);
+            }
+            // Release notes often hold HTML comments from their templates
+            _ => {}
+        }
+    }
+    builder.flush();
+    builder.blocks
+}
+
+pub fn render_markdown(ui: &mut Ui, markdown: &str, color: Color32) {
+    for block in markdown_blocks(markdown) {
+        match block {
+            Block::Heading(level, spans) => {
+                let size = match level {
+                    1 => 20.,
+                    2 => 17.,
+                    _ => 15.,
+                };
+                ui.add_space(4.);
+                ui.horizontal_wrapped(|ui| render_spans(ui, &spans, color, Some(size)));
+            }
+            Block::Paragraph { indent, marker, spans } => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(indent as f32 * 16.);
+                    if let Some(marker) = marker {
+                        ui.label(RichText::new(format!("{} ", marker)).color(color));
+                    }
+                    render_spans(ui, &spans, color, None);
+                });
+            }
+            Block::Code(code) => {
+                egui::Frame::none()
+                    .fill(ui.visuals().code_bg_color)
+                    .inner_margin(6.)
+                    .show(ui, |ui| ui.label(RichText::new(code).monospace().color(color)));
+            }
+            Block::Rule => {
+                ui.separator();
+            }
+        }
+    }
+}
+
+// Inside a wrapped row, so the spans flow on as one line of text
+fn render_spans(ui: &mut Ui, spans: &[Span], color: Color32, heading_size: Option<f32>) {
+    ui.spacing_mut().item_spacing.x = 0.;
+    for span in spans {
+        let mut text = RichText::new(&span.text).color(color);
+        if let Some(size) = heading_size {
+            text = text.size(size).strong();
+        }
+        if span.strong {
+            text = text.strong();
+        }
+        if span.emphasis {
+            text = text.italics();
+        }
+        if span.strikethrough {
+            text = text.strikethrough();
+        }
+        if span.code {
+            text = text.code();
+        }
+        match &span.link {
+            Some(url) => {
+                ui.hyperlink_to(text, url);
+            }
+            None => {
+                ui.label(text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Span {
+        Span {
+            text: text.to_string(),
+            ..Span::default()
+        }
+    }
+
+    #[test]
+    fn splits_release_notes_into_blocks() {
+        let notes = "## What's Changed\n<!-- Release template -->\n* Faster **beat** detection by @someone\n* Fixed `--help`\n  1. on Windows\n\n---\n```\ncargo run\n```";
+        let blocks = markdown_blocks(notes);
+        assert_eq!(blocks[0], Block::Heading(2, vec![text("What's Changed")]));
+        assert_eq!(blocks[1], Block::Paragraph {
+            indent: 1,
+            marker: Some("•".to_string()),
+            spans: vec![text("Faster "), Span { text: "beat".to_string(), strong: true, ..Span::default() }, text(" detection by @someone")],
+        });
+        assert_eq!(blocks[2], Block::Paragraph {
+            indent: 1,
+            marker: Some("•".to_string()),
+            spans: vec![text("Fixed "), Span { text: "--help".to_string(), code: true, ..Span::default() }],
+        });
+        assert_eq!(blocks[3], Block::Paragraph { indent: 2, marker: Some("1.".to_string()), spans: vec![text("on Windows")] });
+        assert_eq!(blocks[4], Block::Rule);
+        assert_eq!(blocks[5], Block::Code("cargo run".to_string()));
+        assert_eq!(blocks.len(), 6);
+    }
+
+    #[test]
+    fn only_web_links_open() {
+        let blocks = markdown_blocks("[Download](https://example.com/a.jar) [run](file:///bin/sh) [relative](docs/notes.md)");
+        let Block::Paragraph { spans, .. } = &blocks[0] else {
+            panic!("Expected a paragraph, got {:?}", blocks[0]);
+        };
+        assert_eq!(spans[0], Span { text: "Download".to_string(), link: Some("https://example.com/a.jar".to_string()), ..Span::default() });
+        assert_eq!(spans[1], text(" run relative"));
+        assert_eq!(spans.len(), 2);
+    }
+}
@@ -1,15 +1,48 @@
 use std::time::Duration;
+use chrono::{DateTime, Utc};
 use crate::gui::{Notification, Timer};
+use crate::rate_limit::format_resume_time;
 
-pub fn rate_limit_notification() -> Notification {
+pub fn rate_limit_notification(resume: Option<DateTime<Utc>>) -> Notification {
+    let resume_message = match resume {
+        Some(time) => format!("Update checks will resume at {}.", format_resume_time(time)),
+        None => "Update checks will resume once Github allows it.".to_string(),
+    };
     Notification {
         title: "Github Rate Limited".to_string(),
-        message: "Open Lights Manager has sent too many requests to Github.\nConsider entering a Github Token in Settings to see updates and new apps".to_string(),
+        message: format!("Open Lights Manager has sent too many requests to Github.\n{}\nConsider entering a Github Token in Settings to see updates and new apps", resume_message),
         timer: Timer::new(Duration::from_secs(30)),
         id: fastrand::i32(0..i32::MAX),
     }
 }
 
+pub fn bad_token_notification() -> Notification {
+    Notification {
+        title: "Github Token Rejected".to_string(),
+        message: "Github did not accept the token entered in Settings.\nCheck that it is valid and has not expired.".to_string(),
+        timer: Timer::new(Duration::from_secs(30)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn github_unreachable_notification() -> Notification {
+    Notification {
+        title: "Github Unreachable".to_string(),
+        message: "Open Lights Manager could not connect to Github.\nEnsure your device is connected to the Internet.".to_string(),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn github_request_failed(error: &str) -> Notification {
+    Notification {
+        title: "Github Request Failed".to_string(),
+        message: format!("{}.\nPlease try again later.", error),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
 pub fn java_failure_corrupted() -> Notification {
     Notification {
         title: "Java Check Failure".to_string(),
@@ -55,6 +88,24 @@ pub fn app_installation_failure(app: &String) -> Notification {
     }
 }
 
+pub fn app_installation_failure_reason(app: &String, reason: &str) -> Notification {
+    Notification {
+        title: "App Installation Failure".to_string(),
+        message: format!("{} has failed to install.\n{}", app, reason),
+        timer: Timer::new(Duration::from_secs(20)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_installation_cancelled(app: &String) -> Notification {
+    Notification {
+        title: "App Installation Cancelled".to_string(),
+        message: format!("{} was left as it was before the download.", app),
+        timer: Timer::new(Duration::from_secs(10)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
 pub fn app_installation_success(app: &String) -> Notification {
     Notification {
         title: "App Installation Successful".to_string(),
@@ -64,6 +115,78 @@ pub fn app_installation_success(app: &String) -> Notification {
     }
 }
 
+pub fn app_update_success(app: &String, previous_version: &str) -> Notification {
+    Notification {
+        title: "App Update Successful".to_string(),
+        message: format!("{} has updated.\nRight-click its icon to roll back to {}.", app, previous_version),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_update_available(app: &String, version: &str) -> Notification {
+    Notification {
+        title: "Update Available".to_string(),
+        message: format!("{} {} is ready to install.", app, version),
+        timer: Timer::new(Duration::from_secs(10)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_version_installed(app: &String, version: &str) -> Notification {
+    Notification {
+        title: "Version Installed".to_string(),
+        message: format!("{} {} is ready to use from its versions.", app, version),
+        timer: Timer::new(Duration::from_secs(10)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_version_activated(app: &String, version: &str) -> Notification {
+    Notification {
+        title: "Version Changed".to_string(),
+        message: format!("{} is now on {}.", app, version),
+        timer: Timer::new(Duration::from_secs(10)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_version_switch_failure(app: &String, reason: &str) -> Notification {
+    Notification {
+        title: "Version Change Failed".to_string(),
+        message: format!("{} could not change versions.\n{}", app, reason),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_rolled_back(app: &String, version: &str) -> Notification {
+    Notification {
+        title: "App Rolled Back".to_string(),
+        message: format!("{} is back on {}.", app, version),
+        timer: Timer::new(Duration::from_secs(10)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_rollback_failure(app: &String, reason: &str) -> Notification {
+    Notification {
+        title: "App Rollback Failed".to_string(),
+        message: format!("{} could not be rolled back.\n{}", app, reason),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
+pub fn app_uninstall_failure(app: &String, reason: &str) -> Notification {
+    Notification {
+        title: "App Uninstall Failed".to_string(),
+        message: format!("{} could not be fully removed. If it is running, close it and try again.\n{}", app, reason),
+        timer: Timer::new(Duration::from_secs(15)),
+        id: fastrand::i32(0..i32::MAX),
+    }
+}
+
 pub fn manager_installation_success() -> Notification {
     Notification {
         title: "Open Lights Manager Installation Successful".to_string(),
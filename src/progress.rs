@@ -0,0 +1,214 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::archive::format_size;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Phase {
+    #[default]
+    Idle,
+    Downloading,
+    Verifying,
+    Extracting,
+}
+
+impl Phase {
+    fn from_u8(value: u8) -> Phase {
+        match value {
+            1 => Phase::Downloading,
+            2 => Phase::Verifying,
+            3 => Phase::Extracting,
+            _ => Phase::Idle,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Phase::Idle => 0,
+            Phase::Downloading => 1,
+            Phase::Verifying => 2,
+            Phase::Extracting => 3,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    phase: AtomicU8,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64, // 0 when unknown
+    files_done: AtomicU64,
+    files_total: AtomicU64, // 0 when unknown
+    started: Mutex<Option<(Instant, u64)>>, // When the current transfer began and how many bytes were already done
+}
+
+// Written by an install thread, read by App::render and the Downloads screen
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    state: Arc<ProgressState>,
+}
+
+impl Progress {
+    // Clears the counts left over from the previous phase
+    pub fn start_phase(&self, phase: Phase, bytes_total: u64) {
+        self.state.phase.store(phase.to_u8(), Ordering::Relaxed);
+        self.state.files_done.store(0, Ordering::Relaxed);
+        self.state.files_total.store(0, Ordering::Relaxed);
+        self.begin(0, bytes_total);
+    }
+
+    // For transfers that pick up partway, like a resumed download
+    pub fn begin(&self, bytes_done: u64, bytes_total: u64) {
+        self.state.bytes_done.store(bytes_done, Ordering::Relaxed);
+        self.state.bytes_total.store(bytes_total, Ordering::Relaxed);
+        *self.state.started.lock().unwrap() = Some((Instant::now(), bytes_done));
+    }
+
+    pub fn finish(&self) {
+        self.start_phase(Phase::Idle, 0);
+        *self.state.started.lock().unwrap() = None;
+    }
+
+    pub fn set_bytes_done(&self, bytes_done: u64) {
+        self.state.bytes_done.store(bytes_done, Ordering::Relaxed);
+    }
+
+    pub fn set_bytes_total(&self, bytes_total: u64) {
+        self.state.bytes_total.store(bytes_total, Ordering::Relaxed);
+    }
+
+    pub fn set_files(&self, files_done: u64, files_total: u64) {
+        self.state.files_done.store(files_done, Ordering::Relaxed);
+        self.state.files_total.store(files_total, Ordering::Relaxed);
+    }
+
+    pub fn file_done(&self) {
+        self.state.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn phase(&self) -> Phase {
+        Phase::from_u8(self.state.phase.load(Ordering::Relaxed))
+    }
+
+    pub fn bytes_done(&self) -> u64 {
+        self.state.bytes_done.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_total(&self) -> u64 {
+        self.state.bytes_total.load(Ordering::Relaxed)
+    }
+
+    pub fn files_done(&self) -> u64 {
+        self.state.files_done.load(Ordering::Relaxed)
+    }
+
+    pub fn files_total(&self) -> u64 {
+        self.state.files_total.load(Ordering::Relaxed)
+    }
+
+    // None while the total is unknown, so the UI can show an indeterminate bar instead of dividing by zero
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.bytes_total();
+        if total > 0 {
+            return Some((self.bytes_done() as f64 / total as f64).min(1.) as f32);
+        }
+        let files_total = self.files_total();
+        if files_total > 0 {
+            return Some((self.files_done() as f64 / files_total as f64).min(1.) as f32);
+        }
+        None
+    }
+
+    // Averaged since the transfer began, so resumed bytes don't inflate it
+    pub fn bytes_per_second(&self) -> Option<f64> {
+        let (started, already_done) = (*self.state.started.lock().unwrap())?;
+        let elapsed = started.elapsed().as_secs_f64();
+        if elapsed < 0.5 {
+            return None;
+        }
+        Some(self.bytes_done().saturating_sub(already_done) as f64 / elapsed)
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.bytes_total();
+        let speed = self.bytes_per_second()?;
+        if total == 0 || speed <= 0. {
+            return None;
+        }
+        Some(Duration::from_secs_f64(total.saturating_sub(self.bytes_done()) as f64 / speed))
+    }
+
+    // Ex: 12.5 MB / 200.0 MB - 1.2 MB/s - 2m 30s left
+    pub fn describe(&self) -> String {
+        let mut description = match self.phase() {
+            Phase::Idle => return String::new(),
+            Phase::Verifying => return "Verifying".to_string(),
+            Phase::Downloading => String::new(),
+            Phase::Extracting if self.files_total() > 0 => format!("{} / {} files - ", self.files_done(), self.files_total()),
+            Phase::Extracting => format!("{} files - ", self.files_done()),
+        };
+        match self.bytes_total() {
+            0 => description.push_str(&format_size(self.bytes_done())),
+            total => description.push_str(&format!("{} / {}", format_size(self.bytes_done()), format_size(total))),
+        }
+        if let Some(speed) = self.bytes_per_second() {
+            description.push_str(&format!(" - {}/s", format_size(speed as u64)));
+        }
+        if let Some(eta) = self.eta() {
+            description.push_str(&format!(" - {} left", format_duration(eta)));
+        }
+        description
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_totals_are_indeterminate() {
+        let progress = Progress::default();
+        progress.start_phase(Phase::Downloading, 0);
+        progress.set_bytes_done(2048);
+        assert_eq!(progress.fraction(), None);
+        assert_eq!(progress.eta(), None);
+        assert_eq!(progress.describe(), "2.0 KB");
+
+        progress.set_bytes_total(4096);
+        assert_eq!(progress.fraction(), Some(0.5));
+        assert_eq!(progress.describe(), "2.0 KB / 4.0 KB");
+    }
+
+    #[test]
+    fn extraction_falls_back_to_file_counts() {
+        let progress = Progress::default();
+        progress.start_phase(Phase::Extracting, 0);
+        progress.set_files(0, 4);
+        progress.file_done();
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert!(progress.describe().starts_with("1 / 4 files"));
+
+        progress.finish();
+        assert_eq!(progress.phase(), Phase::Idle);
+        assert_eq!(progress.describe(), "");
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(150)), "2m 30s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 2m");
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::serde::ts_seconds_option;
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use chrono::format::StrftimeItems;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+
+// Tracks Github's rate limit from the headers of every response
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub remaining: Option<u32>,
+    #[serde(with = "ts_seconds_option")]
+    pub reset: Option<DateTime<Utc>>,
+    // Secondary rate limits only send Retry-After, which holds off every request regardless of what's left
+    #[serde(default, with = "ts_seconds_option")]
+    pub retry_after_until: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    pub fn update(&mut self, headers: &HeaderMap) {
+        if let Some(remaining) = header_number(headers, "x-ratelimit-remaining") {
+            self.remaining = Some(remaining as u32);
+        }
+        if let Some(reset) = header_number(headers, "x-ratelimit-reset") {
+            self.reset = Utc.timestamp_opt(reset as i64, 0).single();
+        }
+        if let Some(retry_after) = header_number(headers, RETRY_AFTER.as_str()) {
+            let retry_at = Utc::now() + Duration::seconds(retry_after as i64);
+            self.retry_after_until = Some(self.retry_after_until.map_or(retry_at, |until| until.max(retry_at)));
+        }
+    }
+
+    // Whether the given number of requests fit in what Github says is left
+    pub fn allows(&self, requests: usize) -> bool {
+        if self.is_backing_off() {
+            return false;
+        }
+        if self.has_reset() {
+            return true;
+        }
+        self.remaining.is_none_or(|remaining| remaining as usize >= requests)
+    }
+
+    pub fn is_limited(&self) -> bool {
+        !self.allows(1)
+    }
+
+    // When Github will accept requests again, if we're currently limited
+    pub fn resumes_at(&self) -> Option<DateTime<Utc>> {
+        if !self.is_limited() {
+            return None;
+        }
+        let backoff = self.retry_after_until.filter(|_| self.is_backing_off());
+        let reset = self.reset.filter(|_| !self.has_reset() && self.remaining == Some(0));
+        backoff.max(reset).or(self.reset)
+    }
+
+    fn is_backing_off(&self) -> bool {
+        self.retry_after_until.is_some_and(|until| until > Utc::now())
+    }
+
+    fn has_reset(&self) -> bool {
+        self.reset.is_none_or(|reset| reset <= Utc::now())
+    }
+}
+
+pub fn format_resume_time(time: DateTime<Utc>) -> String {
+    let local_time = time.with_timezone(&Local::now().timezone());
+    local_time.format_with_items(StrftimeItems::new("%I:%M:%S %p - %m/%d/%Y")).to_string()
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn reads_the_rate_limit_headers() {
+        let reset = Utc::now() + Duration::minutes(30);
+        let mut rate_limit = RateLimit::default();
+        rate_limit.update(&headers(&[("x-ratelimit-remaining", "2"), ("x-ratelimit-reset", &reset.timestamp().to_string())]));
+        assert_eq!(rate_limit.remaining, Some(2));
+        assert_eq!(rate_limit.reset.map(|reset| reset.timestamp()), Some(reset.timestamp()));
+        assert!(rate_limit.allows(2));
+        assert!(!rate_limit.allows(3));
+        assert_eq!(rate_limit.resumes_at(), None);
+
+        // Headers that can't be read leave the last known values alone
+        rate_limit.update(&headers(&[("x-ratelimit-remaining", "lots")]));
+        assert_eq!(rate_limit.remaining, Some(2));
+
+        rate_limit.update(&headers(&[("x-ratelimit-remaining", "0")]));
+        assert!(rate_limit.is_limited());
+        assert_eq!(rate_limit.resumes_at().map(|resumes| resumes.timestamp()), Some(reset.timestamp()));
+
+        // Once the reset time passes the old count no longer applies
+        rate_limit.reset = Some(Utc::now() - Duration::seconds(1));
+        assert!(!rate_limit.is_limited());
+    }
+
+    #[test]
+    fn retry_after_backs_off_on_its_own() {
+        let reset = Utc::now() + Duration::minutes(30);
+        let mut rate_limit = RateLimit { remaining: Some(4000), reset: Some(reset), ..RateLimit::default() };
+        rate_limit.update(&headers(&[("retry-after", "60")]));
+        // The primary limit is untouched
+        assert_eq!((rate_limit.remaining, rate_limit.reset), (Some(4000), Some(reset)));
+        assert!(rate_limit.is_limited());
+        let resumes = rate_limit.resumes_at().unwrap();
+        assert!(resumes > Utc::now() + Duration::seconds(55) && resumes < reset);
+
+        // A shorter Retry-After doesn't cut an earlier one short
+        rate_limit.update(&headers(&[("retry-after", "5")]));
+        assert_eq!(rate_limit.resumes_at(), Some(resumes));
+
+        // Both limits at once wait for whichever ends last
+        rate_limit.remaining = Some(0);
+        assert_eq!(rate_limit.resumes_at(), Some(reset));
+
+        rate_limit.remaining = Some(4000);
+        rate_limit.retry_after_until = Some(Utc::now() - Duration::seconds(1));
+        assert!(!rate_limit.is_limited());
+        assert_eq!(rate_limit.resumes_at(), None);
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::settings::Settings;
+
+pub const DEFAULT_CHECK_INTERVAL_MINUTES: u32 = 60;
+pub const MIN_CHECK_INTERVAL_MINUTES: u32 = 15;
+
+// Github's hourly request limits, without and with a token
+const UNAUTHENTICATED_HOURLY_LIMIT: usize = 60;
+const AUTHENTICATED_HOURLY_LIMIT: usize = 5000;
+
+// A check that failed or wasn't allowed is tried again after this long
+const RETRY_MINUTES: i64 = 10;
+
+// Decides when the background update checks run
+#[derive(Debug, Default)]
+pub struct UpdateScheduler {
+    not_before: Option<DateTime<Utc>>, // Holds off after a failed or rate limited attempt
+}
+
+impl UpdateScheduler {
+    // Whether a check costing this many Github requests should start now
+    pub fn is_due(&self, settings: &Settings, cost: usize, now: DateTime<Utc>) -> bool {
+        if !settings.scheduled_update_checks || self.not_before.is_some_and(|not_before| now < not_before) {
+            return false;
+        }
+        match settings.last_github_check {
+            Some(last_check) => now >= last_check + check_interval(settings, cost),
+            None => true,
+        }
+    }
+
+    // How long until is_due could change, for waking the UI up
+    pub fn time_until_due(&self, settings: &Settings, cost: usize, now: DateTime<Utc>) -> Duration {
+        let due = settings.last_github_check.map_or(now, |last_check| last_check + check_interval(settings, cost));
+        let due = self.not_before.map_or(due, |not_before| due.max(not_before));
+        (due - now).max(Duration::zero())
+    }
+
+    // Successful checks move last_github_check on; this covers the ones that don't
+    pub fn started(&mut self, now: DateTime<Utc>) {
+        self.not_before = Some(now + Duration::minutes(RETRY_MINUTES));
+    }
+
+    pub fn rate_limited(&mut self, settings: &Settings, now: DateTime<Utc>) {
+        self.not_before = Some(settings.rate_limit.resumes_at().unwrap_or(now + Duration::minutes(RETRY_MINUTES)));
+    }
+}
+
+// The configured interval, stretched so background checks use at most half of Github's hourly limit
+pub fn check_interval(settings: &Settings, cost: usize) -> Duration {
+    let hourly_limit = if settings.github_token.trim().is_empty() {
+        UNAUTHENTICATED_HOURLY_LIMIT
+    } else {
+        AUTHENTICATED_HOURLY_LIMIT
+    };
+    let minimum_minutes = (60 * cost).div_ceil(hourly_limit / 2) as i64;
+    let configured_minutes = settings.update_check_interval_minutes.max(MIN_CHECK_INTERVAL_MINUTES) as i64;
+    Duration::minutes(configured_minutes.max(minimum_minutes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::RateLimit;
+
+    fn settings_checked_at(last_check: DateTime<Utc>) -> Settings {
+        Settings {
+            last_github_check: Some(last_check),
+            update_check_interval_minutes: 30,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn intervals_stay_within_the_rate_limit() {
+        let mut settings = settings_checked_at(Utc::now());
+        assert_eq!(check_interval(&settings, 4), Duration::minutes(30));
+        // 20 apps without a token would use 40 of the 60 requests every hour
+        assert_eq!(check_interval(&settings, 40), Duration::minutes(80));
+        settings.github_token = "token".to_string();
+        assert_eq!(check_interval(&settings, 40), Duration::minutes(30));
+
+        settings.update_check_interval_minutes = 1;
+        assert_eq!(check_interval(&settings, 1), Duration::minutes(MIN_CHECK_INTERVAL_MINUTES as i64));
+    }
+
+    #[test]
+    fn checks_wait_for_the_interval_and_back_off() {
+        let now = Utc::now();
+        let settings = settings_checked_at(now - Duration::minutes(20));
+        let mut scheduler = UpdateScheduler::default();
+        assert!(!scheduler.is_due(&settings, 2, now));
+        assert_eq!(scheduler.time_until_due(&settings, 2, now), Duration::minutes(10));
+        assert!(scheduler.is_due(&settings, 2, now + Duration::minutes(10)));
+
+        // A check that fails leaves last_github_check alone, so it waits before retrying
+        scheduler.started(now + Duration::minutes(10));
+        assert!(!scheduler.is_due(&settings, 2, now + Duration::minutes(15)));
+        assert!(scheduler.is_due(&settings, 2, now + Duration::minutes(20)));
+
+        let limited = Settings {
+            rate_limit: RateLimit { remaining: Some(0), reset: Some(now + Duration::hours(1)), ..RateLimit::default() },
+            ..settings_checked_at(now - Duration::hours(2))
+        };
+        scheduler.rate_limited(&limited, now);
+        assert!(!scheduler.is_due(&limited, 2, now + Duration::minutes(30)));
+        assert!(scheduler.is_due(&limited, 2, now + Duration::hours(1)));
+
+        let disabled = Settings { scheduled_update_checks: false, ..limited };
+        assert!(!scheduler.is_due(&disabled, 2, now + Duration::hours(2)));
+    }
+}
@@ -7,7 +7,13 @@ use chrono::{DateTime, Duration, Local, Utc};
 use chrono::format::StrftimeItems;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::archive::ExtractionLimits;
+use crate::download_queue::DEFAULT_MAX_CONCURRENT_DOWNLOADS;
+use crate::rate_limit::RateLimit;
+use crate::scheduler::DEFAULT_CHECK_INTERVAL_MINUTES;
+use crate::source::DEFAULT_RELEASE_PAGE_LIMIT;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub unstable_releases: bool,
     pub dark_theme: bool,
@@ -18,6 +24,14 @@ pub struct Settings {
     #[serde(skip)]
     pub last_github_check_formatted: String,
     pub override_rate_limit: bool,
+    pub rate_limit: RateLimit,
+    pub batch_update_checks: bool,
+    pub release_page_limit: usize, // How many pages of releases to search for one on the chosen channel
+    pub extraction_limits: ExtractionLimits,
+    pub max_concurrent_downloads: usize,
+    pub scheduled_update_checks: bool,
+    pub update_check_interval_minutes: u32, // Stretched when the apps would use too much of the rate limit
+    pub auto_install_updates: bool, // Only for apps whose update policy installs automatically
 }
 
 impl Default for Settings {
@@ -30,6 +44,14 @@ impl Default for Settings {
             last_github_check: Some(Utc::now() - Duration::hours(1)),
             override_rate_limit: false,
             last_github_check_formatted: (Utc::now() - Duration::hours(1)).format("%H:%M:%S - %m/%d/%Y").to_string(),
+            rate_limit: RateLimit::default(),
+            batch_update_checks: false,
+            release_page_limit: DEFAULT_RELEASE_PAGE_LIMIT,
+            extraction_limits: ExtractionLimits::default(),
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            scheduled_update_checks: true,
+            update_check_interval_minutes: DEFAULT_CHECK_INTERVAL_MINUTES,
+            auto_install_updates: true,
         }
     }
 }
@@ -43,6 +65,14 @@ struct MigrationSettings {
     #[serde(with = "ts_seconds_option")]
     pub last_github_check: Option<DateTime<Utc>>,
     pub override_rate_limit: Option<bool>,
+    pub rate_limit: Option<RateLimit>,
+    pub batch_update_checks: Option<bool>,
+    pub release_page_limit: Option<usize>,
+    pub extraction_limits: Option<ExtractionLimits>,
+    pub max_concurrent_downloads: Option<usize>,
+    pub scheduled_update_checks: Option<bool>,
+    pub update_check_interval_minutes: Option<u32>,
+    pub auto_install_updates: Option<bool>,
 }
 
 impl Settings {
@@ -64,6 +94,13 @@ impl Settings {
         self.save_formatted_time();
     }
 
+    // Keeps what a check on another thread learned from Github without undoing changes made while it ran
+    pub fn merge_github_state(&mut self, checked: &Settings) {
+        self.rate_limit = checked.rate_limit.clone();
+        self.last_github_check = checked.last_github_check;
+        self.save_settings();
+    }
+
     fn save_formatted_time(&mut self) {
         let local_last_github_check = self.last_github_check.unwrap().with_timezone(&Local::now().timezone());
         let formatted_time = local_last_github_check.format_with_items(StrftimeItems::new("%I:%M:%S %p - %m/%d/%Y")).to_string();
@@ -85,6 +122,14 @@ fn fix_settings(buf_reader: BufReader<File>) -> Settings {
             Some(Utc::now() - Duration::hours(1))
         };
         settings.override_rate_limit = scavenged_json.override_rate_limit.unwrap_or(settings.override_rate_limit);
+        settings.rate_limit = scavenged_json.rate_limit.unwrap_or_default();
+        settings.batch_update_checks = scavenged_json.batch_update_checks.unwrap_or(settings.batch_update_checks);
+        settings.release_page_limit = scavenged_json.release_page_limit.unwrap_or(settings.release_page_limit);
+        settings.extraction_limits = scavenged_json.extraction_limits.unwrap_or_default();
+        settings.max_concurrent_downloads = scavenged_json.max_concurrent_downloads.unwrap_or(settings.max_concurrent_downloads);
+        settings.scheduled_update_checks = scavenged_json.scheduled_update_checks.unwrap_or(settings.scheduled_update_checks);
+        settings.update_check_interval_minutes = scavenged_json.update_check_interval_minutes.unwrap_or(settings.update_check_interval_minutes);
+        settings.auto_install_updates = scavenged_json.auto_install_updates.unwrap_or(settings.auto_install_updates);
     }
     settings.save_settings();
     settings
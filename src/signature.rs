@@ -0,0 +1,205 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+use crate::asset_match::asset_filename;
+use crate::checksum::fetch_text;
+use crate::gui::{AssetData, ReleaseData};
+use crate::io::GithubError;
+
+// How strictly an app's downloads are checked against the keys pinned in its descriptor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignaturePolicy {
+    Require, // Refuse anything without a valid signature
+    #[default]
+    WhenSigned, // Check signatures that were published, install unsigned releases
+    Skip,
+}
+
+impl SignaturePolicy {
+    pub const ALL: [SignaturePolicy; 3] = [SignaturePolicy::Require, SignaturePolicy::WhenSigned, SignaturePolicy::Skip];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SignaturePolicy::Require => "Require signatures",
+            SignaturePolicy::WhenSigned => "Check when signed",
+            SignaturePolicy::Skip => "Skip signatures",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureError {
+    Unsigned, // No {file}.minisig in the release
+    NoTrustedKey, // The descriptor pins no key to check against
+    Invalid(String),
+    Fetch(GithubError),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Unsigned => write!(f, "The release is not signed"),
+            SignatureError::NoTrustedKey => write!(f, "No signing key is pinned for this app"),
+            SignatureError::Invalid(error) => write!(f, "The signature could not be verified: {}", error),
+            SignatureError::Fetch(error) => write!(f, "Failed to fetch the signature: {}", error),
+        }
+    }
+}
+
+// Ok(true) when a pinned key verified the file, Ok(false) when it was allowed through unchecked
+pub async fn verify_download(path: &Path, release_data: &ReleaseData, asset: &AssetData, public_keys: &[String], policy: SignaturePolicy, token: &str) -> Result<bool, SignatureError> {
+    if policy == SignaturePolicy::Skip {
+        return Ok(false);
+    }
+
+    let signature_name = format!("{}.minisig", asset_filename(asset)).to_ascii_lowercase();
+    let signature_asset = release_data.assets.iter().find(|other| asset_filename(other).to_ascii_lowercase() == signature_name);
+    let signature = match signature_asset {
+        Some(signature_asset) => fetch_text(&signature_asset.browser_download_url, token).await.map_err(SignatureError::Fetch)?,
+        None if policy == SignaturePolicy::Require => return Err(SignatureError::Unsigned),
+        None => {
+            println!("{} is not signed", asset_filename(asset));
+            return Ok(false);
+        }
+    };
+
+    if public_keys.is_empty() {
+        return match policy {
+            SignaturePolicy::Require => Err(SignatureError::NoTrustedKey),
+            _ => {
+                println!("{} is signed but no key is pinned to check it", asset_filename(asset));
+                Ok(false)
+            }
+        };
+    }
+    verify_file(path, &signature, public_keys).map(|_| true)
+}
+
+// Passes if any one of the pinned keys made the signature
+pub fn verify_file(path: &Path, signature: &str, public_keys: &[String]) -> Result<(), SignatureError> {
+    let signature = Signature::decode(signature).map_err(|e| SignatureError::Invalid(e.to_string()))?;
+    let mut last_error = SignatureError::NoTrustedKey;
+    for public_key in public_keys {
+        let public_key = match PublicKey::from_base64(public_key.trim()) {
+            Ok(public_key) => public_key,
+            Err(e) => {
+                println!("Ignoring invalid public key {}: {}", public_key, e);
+                continue;
+            }
+        };
+        match verify_with(path, &signature, &public_key) {
+            Ok(()) => return Ok(()),
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+fn verify_with(path: &Path, signature: &Signature, public_key: &PublicKey) -> Result<(), SignatureError> {
+    let invalid = |e: minisign_verify::Error| SignatureError::Invalid(e.to_string());
+    let mut file = File::open(path).map_err(|e| SignatureError::Invalid(e.to_string()))?;
+    match public_key.verify_stream(signature) {
+        Ok(mut verifier) => {
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = file.read(&mut buffer).map_err(|e| SignatureError::Invalid(e.to_string()))?;
+                if read == 0 {
+                    return verifier.finalize().map_err(invalid);
+                }
+                verifier.update(&buffer[..read]);
+            }
+        }
+        // Signatures from older minisign versions sign the whole file rather than its hash
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| SignatureError::Invalid(e.to_string()))?;
+            public_key.verify(&contents, signature, true).map_err(invalid)
+        }
+        Err(e) => Err(invalid(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    // Test vector from minisign, signing the text "test"
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+    const OTHER_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO4";
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("openlightsmanager-signature-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn release(assets: &[&Path]) -> ReleaseData {
+        ReleaseData {
+            tag_name: "v1.0.0".to_string(),
+            prerelease: false,
+            draft: false,
+            id: 1,
+            assets: assets.iter()
+                .map(|path| AssetData {
+                    size: 0,
+                    browser_download_url: format!("file://{}", path.to_string_lossy()),
+                    digest: None,
+                })
+                .collect(),
+            ..ReleaseData::default()
+        }
+    }
+
+    #[test]
+    fn verifies_against_any_pinned_key() {
+        let dir = temp_dir();
+        let file = dir.join("test");
+        std::fs::write(&file, "test").unwrap();
+
+        assert_eq!(verify_file(&file, SIGNATURE, &[OTHER_KEY.to_string(), PUBLIC_KEY.to_string()]), Ok(()));
+        assert!(verify_file(&file, SIGNATURE, &[OTHER_KEY.to_string()]).is_err());
+
+        std::fs::write(&file, "Test").unwrap();
+        assert!(matches!(verify_file(&file, SIGNATURE, &[PUBLIC_KEY.to_string()]), Err(SignatureError::Invalid(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn policy_decides_what_happens_to_unsigned_releases() {
+        let dir = temp_dir();
+        let file = dir.join("test");
+        std::fs::write(&file, "test").unwrap();
+        let keys = [PUBLIC_KEY.to_string()];
+        let rt = Runtime::new().unwrap();
+
+        let unsigned = release(&[&file]);
+        let asset = &unsigned.assets[0];
+        assert_eq!(rt.block_on(verify_download(&file, &unsigned, asset, &keys, SignaturePolicy::Require, "")), Err(SignatureError::Unsigned));
+        assert_eq!(rt.block_on(verify_download(&file, &unsigned, asset, &keys, SignaturePolicy::WhenSigned, "")), Ok(false));
+        assert_eq!(rt.block_on(verify_download(&file, &unsigned, asset, &keys, SignaturePolicy::Skip, "")), Ok(false));
+
+        let signature = dir.join("test.minisig");
+        std::fs::write(&signature, SIGNATURE).unwrap();
+        let signed = release(&[&file, &signature]);
+        let asset = &signed.assets[0];
+        assert_eq!(rt.block_on(verify_download(&file, &signed, asset, &keys, SignaturePolicy::WhenSigned, "")), Ok(true));
+        assert_eq!(rt.block_on(verify_download(&file, &signed, asset, &[], SignaturePolicy::Require, "")), Err(SignatureError::NoTrustedKey));
+
+        // A published signature is always checked, even when unsigned releases are allowed
+        std::fs::write(&file, "tampered").unwrap();
+        assert!(rt.block_on(verify_download(&file, &signed, asset, &keys, SignaturePolicy::WhenSigned, "")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
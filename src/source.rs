@@ -0,0 +1,262 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gui::{GithubData, ReleaseData};
+use crate::io::{get_json, get_json_page, local_file_path, parse_json, select_release_where, GithubError};
+use crate::rate_limit::RateLimit;
+use crate::tasks::block_on;
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_RELEASE_PAGE_LIMIT: usize = 5;
+
+// How far to page through a source's releases
+#[derive(Clone, Copy)]
+pub struct ReleaseQuery<'a> {
+    pub prerelease: bool, // Paging stops once a release on this channel turns up
+    pub max_pages: usize,
+    pub list_all: bool, // Keeps paging up to max_pages instead, for listing every version
+    pub allows: &'a dyn Fn(&ReleaseData) -> bool, // An app's update policy; releases it rules out don't stop the paging
+}
+
+// For queries that take whatever release comes first
+pub fn allow_any(_release: &ReleaseData) -> bool {
+    true
+}
+
+// Somewhere releases can be listed from; the Github structs double as the common format
+pub trait ReleaseSource {
+    // Repository data and its releases, newest first
+    fn releases(&self, project: &str, query: &ReleaseQuery<'_>, github_token: &str, rate_limit: &mut RateLimit) -> Result<(GithubData, Vec<ReleaseData>), GithubError>;
+}
+
+// Follows the Link headers Github and Gitea send until the query is satisfied or runs out of pages
+fn list_releases(url: &str, query: &ReleaseQuery<'_>, token: &str, rate_limit: &mut RateLimit) -> Result<Vec<ReleaseData>, GithubError> {
+    let mut release_data = Vec::new();
+    let mut next = Some(url.to_string());
+    let mut pages = 0;
+    while let Some(url) = next {
+        let (response, next_url) = block_on(get_json_page(&url, token, rate_limit))?;
+        release_data.extend(parse_json::<Vec<ReleaseData>>(&response)?);
+        pages += 1;
+        if pages >= query.max_pages || (!query.list_all && select_release_where(&release_data, query.prerelease, query.allows).is_some()) {
+            break;
+        }
+        next = next_url;
+    }
+    Ok(release_data)
+}
+
+// Chosen per app in the catalog, Ex: { "type": "gitea", "base_url": "https://git.example.com" }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceConfig {
+    Github(GithubSource),
+    Gitea(GiteaSource),
+    Index(IndexSource),
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        SourceConfig::Github(GithubSource::default())
+    }
+}
+
+impl SourceConfig {
+    pub fn release_source(&self) -> &dyn ReleaseSource {
+        match self {
+            SourceConfig::Github(source) => source,
+            SourceConfig::Gitea(source) => source,
+            SourceConfig::Index(source) => source,
+        }
+    }
+
+    // Only github.com shares the rate limit and token kept in Settings
+    pub fn is_github(&self) -> bool {
+        matches!(self, SourceConfig::Github(source) if source.is_github_dot_com())
+    }
+
+    // The token to send to this source, so the Github token never leaks to other hosts
+    pub fn token<'a>(&'a self, github_token: &'a str) -> &'a str {
+        match self {
+            SourceConfig::Github(source) => source.token(github_token),
+            SourceConfig::Gitea(source) => &source.token,
+            SourceConfig::Index(_) => "",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct GithubSource {
+    #[serde(default = "default_github_api_url")]
+    pub api_url: String, // Ex: https://github.example.com/api/v3 for Github Enterprise
+    #[serde(default)]
+    pub token: Option<String>, // Overrides the Settings token, needed for Enterprise
+}
+
+impl Default for GithubSource {
+    fn default() -> Self {
+        GithubSource {
+            api_url: default_github_api_url(),
+            token: None,
+        }
+    }
+}
+
+fn default_github_api_url() -> String {
+    GITHUB_API_URL.to_string()
+}
+
+impl GithubSource {
+    fn is_github_dot_com(&self) -> bool {
+        self.api_url.trim_end_matches('/') == GITHUB_API_URL
+    }
+
+    fn token<'a>(&'a self, github_token: &'a str) -> &'a str {
+        match &self.token {
+            Some(token) => token,
+            None if self.is_github_dot_com() => github_token,
+            None => "",
+        }
+    }
+}
+
+impl ReleaseSource for GithubSource {
+    fn releases(&self, project: &str, query: &ReleaseQuery<'_>, github_token: &str, rate_limit: &mut RateLimit) -> Result<(GithubData, Vec<ReleaseData>), GithubError> {
+        let token = self.token(github_token);
+        // Enterprise servers have their own limits that shouldn't gate github.com
+        let mut enterprise_rate_limit = RateLimit::default();
+        let rate_limit = if self.is_github_dot_com() { rate_limit } else { &mut enterprise_rate_limit };
+
+        let url = format!("{}/repos/{}", self.api_url.trim_end_matches('/'), project);
+        let response = block_on(get_json(&url, token, rate_limit))?;
+        let github_data = parse_json::<GithubData>(&response)?;
+
+        let release_repo_url = &github_data.releases_url;
+        let modified_repo_url = release_repo_url.replace("{/id}", "");
+        let release_data = list_releases(&modified_repo_url, query, token, rate_limit)?;
+        Ok((github_data, release_data))
+    }
+}
+
+// Gitea and Forgejo share the same API
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct GiteaSource {
+    pub base_url: String, // Ex: https://git.example.com
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaRepository {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl GiteaSource {
+    fn repo_url(&self, project: &str) -> String {
+        format!("{}/api/v1/repos/{}", self.base_url.trim_end_matches('/'), project)
+    }
+
+    fn github_data(&self, project: &str, rate_limit: &mut RateLimit) -> Result<GithubData, GithubError> {
+        let repo_url = self.repo_url(project);
+        let repository = parse_json::<GiteaRepository>(&block_on(get_json(&repo_url, &self.token, rate_limit))?)?;
+        Ok(GithubData {
+            description: repository.description,
+            archived: repository.archived,
+            releases_url: format!("{}/releases{{/id}}", repo_url),
+        })
+    }
+}
+
+impl ReleaseSource for GiteaSource {
+    fn releases(&self, project: &str, query: &ReleaseQuery<'_>, _github_token: &str, _rate_limit: &mut RateLimit) -> Result<(GithubData, Vec<ReleaseData>), GithubError> {
+        // Gitea's rate limiting is unrelated to Github's
+        let mut rate_limit = RateLimit::default();
+        let github_data = self.github_data(project, &mut rate_limit)?;
+        // Gitea releases use the same field names as Github's
+        let releases_url = format!("{}/releases", self.repo_url(project));
+        let release_data = list_releases(&releases_url, query, &self.token, &mut rate_limit)?;
+        Ok((github_data, release_data))
+    }
+}
+
+// A static JSON file listing releases, served over HTTP or read from a file:// URL
+// Format: { "description": "...", "archived": false, "releases": [ <Github release objects> ] }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct IndexSource {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct ReleaseIndex {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    archived: bool,
+    releases: Vec<ReleaseData>,
+}
+
+impl ReleaseSource for IndexSource {
+    fn releases(&self, _project: &str, _query: &ReleaseQuery<'_>, _github_token: &str, _rate_limit: &mut RateLimit) -> Result<(GithubData, Vec<ReleaseData>), GithubError> {
+        let response = if let Some(path) = local_file_path(&self.url)? {
+            fs::read_to_string(path).map_err(|e| GithubError::Io(e.to_string()))?
+        } else {
+            block_on(get_json(&self.url, "", &mut RateLimit::default()))?
+        };
+        let index = parse_json::<ReleaseIndex>(&response)?;
+
+        let github_data = GithubData {
+            description: index.description,
+            archived: index.archived,
+            releases_url: self.url.clone(),
+        };
+        Ok((github_data, index.releases))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn keeps_paging_past_releases_the_policy_rules_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/releases", listener.local_addr().unwrap());
+        let next = format!("{}?page=2", url);
+        let server = thread::spawn(move || {
+            let pages = [
+                (r#"[{"tag_name": "v2.0.0", "prerelease": false, "draft": false, "id": 2, "assets": []}]"#, Some(next)),
+                (r#"[{"tag_name": "v1.5.0", "prerelease": false, "draft": false, "id": 1, "assets": []}]"#, None),
+            ];
+            let mut served = 0;
+            for (body, next) in pages {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    break;
+                };
+                for line in BufReader::new(&stream).lines() {
+                    if line.unwrap().is_empty() {
+                        break;
+                    }
+                }
+                let link = next.map_or(String::new(), |next| format!("Link: <{}>; rel=\"next\"\r\n", next));
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}", body.len(), link, body).unwrap();
+                served += 1;
+            }
+            served
+        });
+
+        let same_major = |release: &ReleaseData| release.tag_name.starts_with("v1.");
+        let query = ReleaseQuery { prerelease: false, max_pages: 5, list_all: false, allows: &same_major };
+        let releases = list_releases(&url, &query, "", &mut RateLimit::default()).unwrap();
+        let tags: Vec<&str> = releases.iter().map(|release| release.tag_name.as_str()).collect();
+        assert_eq!(tags, ["v2.0.0", "v1.5.0"]);
+        assert_eq!(server.join().unwrap(), 2);
+    }
+}
@@ -0,0 +1,334 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+pub const APPS_DIR: &str = "openlightsmanager/apps";
+
+// Where one app's files live while it is downloaded, staged, installed and kept side by side
+// Everything sits in the same folder so switching versions is a rename, never a copy
+#[derive(Debug, Clone)]
+pub struct AppDirs {
+    pub app: PathBuf, // The active version, where launching and the Java path look
+    pub download: PathBuf, // Downloads, kept between attempts so they can resume
+    pub staging: PathBuf, // The next version, built up here until it is verified
+    pub versions: PathBuf, // Every other installed version, one folder per tag
+    pub extracting: PathBuf, // Archives with an extra top folder are unpacked here first
+}
+
+// Saved next to each kept version's folder
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionRecord {
+    tag: String, // Folder names can't hold every tag
+    #[serde(default)]
+    rollback: bool, // Kept by an update rather than installed side by side, so the next update replaces it
+}
+
+impl AppDirs {
+    pub fn new(name: &str) -> Self {
+        AppDirs::within(Path::new(APPS_DIR), name)
+    }
+
+    pub fn within(apps_dir: &Path, name: &str) -> Self {
+        AppDirs {
+            app: apps_dir.join(name),
+            download: apps_dir.join(format!(".{}-download", name)),
+            staging: apps_dir.join(format!(".{}-staging", name)),
+            versions: apps_dir.join(format!(".{}-versions", name)),
+            extracting: apps_dir.join(format!(".{}-extracting", name)),
+        }
+    }
+
+    pub fn version(&self, version: &str) -> PathBuf {
+        self.versions.join(version_dir_name(version))
+    }
+
+    pub fn has_version(&self, version: &str) -> bool {
+        self.version(version).is_dir()
+    }
+
+    // The tags of the inactive versions on disk
+    pub fn installed_versions(&self) -> Vec<String> {
+        self.records().into_iter().map(|(_, record)| record.tag).collect()
+    }
+
+    // Empties the staging folder; non-archive updates start from a copy of the installed app so files it keeps next to itself carry over
+    pub fn prepare_staging(&self, keep_installed_files: bool) -> io::Result<()> {
+        remove_if_exists(&self.staging)?;
+        if keep_installed_files && self.app.is_dir() {
+            copy_dir(&self.app, &self.staging)
+        } else {
+            fs::create_dir_all(&self.staging)
+        }
+    }
+
+    // The staged version has to contain the executable the descriptor points at
+    pub fn verify_staged(&self, executable: &str) -> io::Result<()> {
+        let executable = executable.trim_start_matches('/');
+        if executable.is_empty() || self.staging.join(executable).exists() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("The new version has no {}", executable)))
+        }
+    }
+
+    // Makes the staged version the active one, keeping the version it replaces
+    // Returns whether that version was kept
+    pub fn swap_in(&self, active_version: Option<&str>, new_version: &str) -> io::Result<bool> {
+        let kept = match active_version {
+            Some(active_version) if self.app.exists() => {
+                self.keep(&self.app, active_version, true)?;
+                Some(self.version(active_version))
+            }
+            // Left over from an install the manager doesn't know about
+            _ => {
+                remove_if_exists(&self.app)?;
+                None
+            }
+        };
+        if let Err(e) = fs::rename(&self.staging, &self.app) {
+            if let Some(kept) = &kept {
+                // Put the old version back rather than leave nothing installed
+                fs::rename(kept, &self.app)?;
+            }
+            return Err(e);
+        }
+        // A copy installed side by side earlier is now the active one
+        self.remove_version(new_version)?;
+        // Only the version just put aside is kept for rollback
+        for (folder, record) in self.records() {
+            if record.rollback && Some(record.tag.as_str()) != active_version {
+                if let Err(e) = self.remove_version(&record.tag) {
+                    println!("Failed to remove {}: {}", folder, e);
+                }
+            }
+        }
+        Ok(kept.is_some())
+    }
+
+    // Keeps the staged version next to the active one without switching to it
+    pub fn store_staged(&self, version: &str) -> io::Result<()> {
+        self.keep(&self.staging, version, false)
+    }
+
+    // Swaps an installed version with the active one, which is kept in its place
+    pub fn activate(&self, active_version: Option<&str>, version: &str) -> io::Result<()> {
+        let target = self.version(version);
+        if !target.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not installed", version)));
+        }
+        let Some(active_version) = active_version else {
+            remove_if_exists(&self.app)?;
+            fs::rename(&target, &self.app)?;
+            return remove_record(&self.record_path(version));
+        };
+        let kept = self.version(active_version);
+        if target == kept {
+            return Ok(());
+        }
+        // Switched away from by choice, so it stays until removed
+        self.keep(&self.app, active_version, false)?;
+        if let Err(e) = fs::rename(&target, &self.app) {
+            fs::rename(&kept, &self.app)?;
+            return Err(e);
+        }
+        remove_record(&self.record_path(version))
+    }
+
+    pub fn remove_version(&self, version: &str) -> io::Result<()> {
+        remove_if_exists(&self.version(version))?;
+        remove_record(&self.record_path(version))
+    }
+
+    // Moves a version's files into the versions folder and records its tag
+    fn keep(&self, from: &Path, version: &str, rollback: bool) -> io::Result<()> {
+        let target = self.version(version);
+        remove_if_exists(&target)?;
+        fs::create_dir_all(&self.versions)?;
+        fs::rename(from, &target)?;
+        let record = VersionRecord { tag: version.to_string(), rollback };
+        fs::write(self.record_path(version), serde_json::to_string(&record)?)
+    }
+
+    fn record_path(&self, version: &str) -> PathBuf {
+        self.versions.join(format!("{}.json", version_dir_name(version)))
+    }
+
+    // Each kept version's folder name and record; folders from before records were kept use the folder name as the tag
+    fn records(&self) -> Vec<(String, VersionRecord)> {
+        let Ok(entries) = self.versions.read_dir() else {
+            return Vec::new();
+        };
+        entries.flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                let folder = entry.file_name().to_string_lossy().to_string();
+                let record = fs::read_to_string(self.versions.join(format!("{}.json", folder))).ok()
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or(VersionRecord { tag: folder.clone(), rollback: false });
+                (folder, record)
+            })
+            .collect()
+    }
+
+    // After a failed or cancelled install; the installed versions are never touched
+    pub fn discard_staging(&self) {
+        for path in [&self.staging, &self.extracting, &self.download] {
+            if let Err(e) = remove_if_exists(path) {
+                println!("Failed to remove {}: {}", path.to_string_lossy(), e);
+            }
+        }
+    }
+
+    pub fn remove_all(&self) -> io::Result<()> {
+        remove_if_exists(&self.app)?;
+        remove_if_exists(&self.versions)?;
+        self.discard_staging();
+        Ok(())
+    }
+}
+
+// Tags can hold characters that aren't allowed in folder names
+fn version_dir_name(version: &str) -> String {
+    version.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+') { c } else { '_' })
+        .collect()
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn remove_record(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in from.read_dir()? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dirs() -> (PathBuf, AppDirs) {
+        let apps_dir = std::env::temp_dir().join(format!("openlightsmanager-staging-{}", fastrand::u64(..)));
+        fs::create_dir_all(&apps_dir).unwrap();
+        let dirs = AppDirs::within(&apps_dir, "BeatMaker");
+        (apps_dir, dirs)
+    }
+
+    fn stage(dirs: &AppDirs, jar: &str) {
+        dirs.prepare_staging(true).unwrap();
+        fs::write(dirs.staging.join("BeatMaker.jar"), jar).unwrap();
+        dirs.verify_staged("/BeatMaker.jar").unwrap();
+    }
+
+    fn active_jar(dirs: &AppDirs) -> String {
+        fs::read_to_string(dirs.app.join("BeatMaker.jar")).unwrap()
+    }
+
+    #[test]
+    fn swaps_in_and_rolls_back() {
+        let (apps_dir, dirs) = temp_dirs();
+        stage(&dirs, "1.0.0");
+        assert!(!dirs.swap_in(None, "v1.0.0").unwrap());
+        fs::write(dirs.app.join("settings.json"), "{}").unwrap();
+
+        stage(&dirs, "1.1.0");
+        assert!(dirs.swap_in(Some("v1.0.0"), "v1.1.0").unwrap());
+        assert_eq!(active_jar(&dirs), "1.1.0");
+        // Files the app wrote next to itself come along to the new version
+        assert!(dirs.app.join("settings.json").exists());
+        assert!(!dirs.staging.exists());
+
+        dirs.activate(Some("v1.1.0"), "v1.0.0").unwrap();
+        assert_eq!(active_jar(&dirs), "1.0.0");
+        assert_eq!(dirs.installed_versions(), vec!["v1.1.0".to_string()]);
+
+        dirs.remove_all().unwrap();
+        assert!(!dirs.app.exists() && !dirs.versions.exists());
+        fs::remove_dir_all(apps_dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_versions_side_by_side() {
+        let (apps_dir, dirs) = temp_dirs();
+        // A version can be picked before anything is installed
+        stage(&dirs, "2.0.0");
+        dirs.store_staged("v2.0.0").unwrap();
+        dirs.activate(None, "v2.0.0").unwrap();
+        assert!(dirs.installed_versions().is_empty());
+
+        stage(&dirs, "1.0.0");
+        dirs.store_staged("season/2024").unwrap();
+        assert!(dirs.has_version("season/2024"));
+        // Listed by its tag, not the folder it was saved under
+        assert_eq!(dirs.installed_versions(), vec!["season/2024".to_string()]);
+        assert_eq!(active_jar(&dirs), "2.0.0");
+
+        dirs.activate(Some("v2.0.0"), "season/2024").unwrap();
+        assert_eq!(active_jar(&dirs), "1.0.0");
+        assert!(dirs.has_version("v2.0.0") && !dirs.has_version("season/2024"));
+
+        // Updating to a version that was already kept side by side doesn't leave a stale copy
+        stage(&dirs, "2.0.0");
+        dirs.swap_in(Some("season/2024"), "v2.0.0").unwrap();
+        assert_eq!(dirs.installed_versions(), vec!["season/2024".to_string()]);
+        fs::remove_dir_all(apps_dir).unwrap();
+    }
+
+    #[test]
+    fn updates_keep_a_single_rollback_version() {
+        let (apps_dir, dirs) = temp_dirs();
+        stage(&dirs, "0.9.0");
+        dirs.store_staged("v0.9.0").unwrap();
+        stage(&dirs, "1.0.0");
+        dirs.swap_in(None, "v1.0.0").unwrap();
+        stage(&dirs, "1.1.0");
+        dirs.swap_in(Some("v1.0.0"), "v1.1.0").unwrap();
+        stage(&dirs, "1.2.0");
+        dirs.swap_in(Some("v1.1.0"), "v1.2.0").unwrap();
+
+        // The version installed side by side stays; only the latest rollback copy is kept
+        let mut versions = dirs.installed_versions();
+        versions.sort();
+        assert_eq!(versions, vec!["v0.9.0".to_string(), "v1.1.0".to_string()]);
+        assert!(!dirs.versions.join("v1.0.0.json").exists());
+        fs::remove_dir_all(apps_dir).unwrap();
+    }
+
+    #[test]
+    fn unverified_stages_leave_the_installed_version_alone() {
+        let (apps_dir, dirs) = temp_dirs();
+        stage(&dirs, "1.0.0");
+        dirs.swap_in(None, "v1.0.0").unwrap();
+
+        dirs.prepare_staging(false).unwrap();
+        fs::write(dirs.staging.join("README.md"), "").unwrap();
+        assert!(dirs.verify_staged("/BeatMaker.jar").is_err());
+        dirs.discard_staging();
+
+        assert!(!dirs.staging.exists());
+        assert_eq!(active_jar(&dirs), "1.0.0");
+        assert!(dirs.activate(Some("v1.0.0"), "v0.9.0").is_err());
+        fs::remove_dir_all(apps_dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use std::future::Future;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use egui::Context;
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::AbortHandle;
+
+// The manager's one runtime; network and disk work runs here so it never blocks a frame
+pub struct Tasks {
+    runtime: Runtime,
+    ctx: Option<Context>, // Repainted when a task finishes so its result is picked up straight away
+}
+
+impl Tasks {
+    pub fn new(ctx: Option<Context>) -> Self {
+        let runtime = Builder::new_multi_thread()
+            .thread_name("openlightsmanager-worker")
+            .enable_all()
+            .build()
+            .unwrap();
+        Tasks {
+            runtime,
+            ctx,
+        }
+    }
+
+    pub fn handle(&self) -> Handle {
+        self.runtime.handle().clone()
+    }
+
+    pub fn spawn<T: Send + 'static>(&self, future: impl Future<Output = T> + Send + 'static) -> Task<T> {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = self.ctx.clone();
+        let join = self.runtime.spawn(async move {
+            let _ = sender.send(future.await);
+            if let Some(ctx) = ctx {
+                ctx.request_repaint();
+            }
+        });
+        Task {
+            receiver,
+            abort: join.abort_handle(),
+        }
+    }
+
+    // For synchronous code that blocks, like the update checks and installs
+    pub fn spawn_blocking<T: Send + 'static>(&self, work: impl FnOnce() -> T + Send + 'static) -> Task<T> {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = self.ctx.clone();
+        let join = self.runtime.spawn_blocking(move || {
+            let _ = sender.send(work());
+            if let Some(ctx) = ctx {
+                ctx.request_repaint();
+            }
+        });
+        Task {
+            receiver,
+            abort: join.abort_handle(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskState<T> {
+    Running,
+    Finished(T),
+    Failed, // Panicked or aborted before sending a result
+}
+
+// Polled once per frame by whatever started the task
+#[derive(Debug)]
+pub struct Task<T> {
+    receiver: Receiver<T>,
+    abort: AbortHandle,
+}
+
+impl<T> Task<T> {
+    pub fn poll(&self) -> TaskState<T> {
+        match self.receiver.try_recv() {
+            Ok(result) => TaskState::Finished(result),
+            Err(TryRecvError::Empty) => TaskState::Running,
+            Err(TryRecvError::Disconnected) => TaskState::Failed,
+        }
+    }
+
+    // Blocking tasks can't be stopped once they have started
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+}
+
+// For synchronous code on one of the runtime's threads, like a download job
+// Outside the manager's runtime (tests and tools) a temporary one is made instead
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(handle) => handle.block_on(future),
+        Err(_) => Runtime::new().unwrap().block_on(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn wait_for<T>(task: &Task<T>) -> TaskState<T> {
+        for _ in 0..200 {
+            match task.poll() {
+                TaskState::Running => thread::sleep(Duration::from_millis(10)),
+                state => return state,
+            }
+        }
+        panic!("Timed out waiting for the task");
+    }
+
+    #[test]
+    fn delivers_results_through_the_handle() {
+        let tasks = Tasks::new(None);
+        let task = tasks.spawn(async { 2 + 2 });
+        assert_eq!(wait_for(&task), TaskState::Finished(4));
+
+        // Blocking work shares the runtime, so it can drive futures itself
+        let task = tasks.spawn_blocking(|| block_on(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            "done"
+        }));
+        assert_eq!(wait_for(&task), TaskState::Finished("done"));
+    }
+
+    #[test]
+    fn aborted_tasks_never_finish() {
+        let tasks = Tasks::new(None);
+        let task = tasks.spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+        task.abort();
+        assert_eq!(wait_for(&task), TaskState::Failed);
+    }
+}
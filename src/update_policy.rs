@@ -0,0 +1,133 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::io::parse_semver;
+
+// What happens once an allowed update is found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateMode {
+    #[default]
+    Notify,
+    AutoInstall,
+}
+
+impl UpdateMode {
+    pub const ALL: [UpdateMode; 2] = [UpdateMode::Notify, UpdateMode::AutoInstall];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UpdateMode::Notify => "Notify me",
+            UpdateMode::AutoInstall => "Install automatically",
+        }
+    }
+}
+
+// Which newer releases count as updates, relative to the installed version
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "range", rename_all = "snake_case")]
+pub enum UpdateRange {
+    #[default]
+    Any,
+    SameMajor,
+    SameMinor,
+    Custom(String), // A semver requirement, Ex: >=2.1, <3
+}
+
+impl UpdateRange {
+    pub fn label(&self) -> &'static str {
+        match self {
+            UpdateRange::Any => "Any newer version",
+            UpdateRange::SameMajor => "Same major version",
+            UpdateRange::SameMinor => "Same minor version",
+            UpdateRange::Custom(_) => "Custom range",
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            UpdateRange::Custom(requirement) => VersionReq::parse(requirement).is_ok(),
+            _ => true,
+        }
+    }
+
+    fn allows(&self, current: &Version, candidate: &Version) -> bool {
+        match self {
+            UpdateRange::Any => true,
+            UpdateRange::SameMajor => candidate.major == current.major,
+            UpdateRange::SameMinor => candidate.major == current.major && candidate.minor == current.minor,
+            UpdateRange::Custom(requirement) => match VersionReq::parse(requirement) {
+                Ok(requirement) => requirement.matches(candidate),
+                Err(e) => {
+                    // Nothing is installed from a range that can't be read
+                    println!("Invalid update range {}: {}", requirement, e);
+                    false
+                }
+            },
+        }
+    }
+}
+
+// Saved with each app
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdatePolicy {
+    #[serde(default)]
+    pub mode: UpdateMode,
+    #[serde(default)]
+    pub range: UpdateRange,
+    #[serde(default)]
+    pub skipped_versions: Vec<String>, // Release tags the user chose to skip
+}
+
+impl UpdatePolicy {
+    pub fn allows(&self, current_version: &str, tag: &str) -> bool {
+        !self.skipped_versions.iter().any(|skipped| skipped == tag)
+            && self.range.allows(&parse_semver(current_version), &parse_semver(tag))
+    }
+
+    pub fn skip(&mut self, tag: &str) {
+        if !self.skipped_versions.iter().any(|skipped| skipped == tag) {
+            self.skipped_versions.push(tag.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_stay_near_the_installed_version() {
+        let mut policy = UpdatePolicy::default();
+        assert!(policy.allows("v1.4.2", "v2.0.0"));
+
+        policy.range = UpdateRange::SameMajor;
+        assert!(policy.allows("v1.4.2", "v1.9.0"));
+        assert!(!policy.allows("v1.4.2", "v2.0.0"));
+
+        policy.range = UpdateRange::SameMinor;
+        assert!(policy.allows("v1.4.2", "v1.4.3"));
+        assert!(!policy.allows("v1.4.2", "v1.5.0"));
+
+        policy.range = UpdateRange::Custom(">=1.5, <3".to_string());
+        assert!(policy.allows("v1.4.2", "v2.9.9"));
+        assert!(!policy.allows("v1.4.2", "v3.0.0"));
+        policy.range = UpdateRange::Custom("not a range".to_string());
+        assert!(!policy.allows("v1.4.2", "v1.5.0"));
+    }
+
+    #[test]
+    fn skipped_versions_are_never_offered() {
+        let mut policy = UpdatePolicy::default();
+        policy.skip("v1.5.0");
+        policy.skip("v1.5.0");
+        assert_eq!(policy.skipped_versions, vec!["v1.5.0".to_string()]);
+        assert!(!policy.allows("v1.4.2", "v1.5.0"));
+        assert!(policy.allows("v1.4.2", "v1.5.1"));
+
+        // Older app JSON has no policy and gets the defaults
+        let policy: UpdatePolicy = serde_json::from_str(r#"{ "range": { "type": "custom", "range": "^1" } }"#).unwrap();
+        assert_eq!(policy.mode, UpdateMode::Notify);
+        assert_eq!(policy.range, UpdateRange::Custom("^1".to_string()));
+    }
+}